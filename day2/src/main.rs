@@ -1,10 +1,153 @@
 use std::{
     cmp::PartialOrd,
+    env,
+    fmt::{self, Display},
     fs::File,
     io::{BufRead, BufReader},
 };
 
 use itertools::Itertools;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+enum Diagnosis {
+    Safe,
+    SafeWithoutLevel { level: usize },
+    StepTooLarge { step: usize },
+    StepTooSmall { step: usize },
+    DirectionChange { step: usize },
+}
+
+impl Diagnosis {
+    fn is_safe_with_dampener(&self) -> bool {
+        matches!(self, Diagnosis::Safe | Diagnosis::SafeWithoutLevel { .. })
+    }
+}
+
+#[derive(Serialize)]
+struct ReportDiagnosis<'a> {
+    report: &'a [u32],
+    #[serde(flatten)]
+    diagnosis: Diagnosis,
+}
+
+impl Display for ReportDiagnosis<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let report = self.report;
+        write!(f, "[{}] ", report.iter().join(" "))?;
+        match self.diagnosis {
+            Diagnosis::Safe => write!(f, "safe"),
+            Diagnosis::SafeWithoutLevel { level } => {
+                write!(f, "safe after removing level {level} ({})", report[level])
+            }
+            Diagnosis::StepTooLarge { step } => write!(
+                f,
+                "unsafe, step {step} ({} -> {}) is too large",
+                report[step],
+                report[step + 1]
+            ),
+            Diagnosis::StepTooSmall { step } => write!(
+                f,
+                "unsafe, step {step} ({} -> {}) does not change",
+                report[step],
+                report[step + 1]
+            ),
+            Diagnosis::DirectionChange { step } => write!(
+                f,
+                "unsafe, step {step} ({} -> {}) changes direction",
+                report[step],
+                report[step + 1]
+            ),
+        }
+    }
+}
+
+fn is_safe_step<F>(f: &F, left: u32, right: u32) -> bool
+where
+    F: Fn(&u32, &u32) -> bool,
+{
+    f(&left, &right) && left.abs_diff(right) <= 3
+}
+
+fn find_failures<F>(report: &[u32], f: &F) -> Vec<(usize, (u32, u32))>
+where
+    F: Fn(&u32, &u32) -> bool,
+{
+    report
+        .iter()
+        .copied()
+        .tuple_windows()
+        .enumerate()
+        .filter(|&(_, (left, right))| !is_safe_step(f, left, right))
+        .collect()
+}
+
+// Index of a level whose removal fixes all the given failures, if there is one
+fn find_removal<F>(report: &[u32], f: &F, failures: &[(usize, (u32, u32))]) -> Option<usize>
+where
+    F: Fn(&u32, &u32) -> bool,
+{
+    match failures[..] {
+        [] => None,
+        [(i, _)] => {
+            if i == 0 {
+                Some(0)
+            } else if i == report.len() - 2 {
+                Some(report.len() - 1)
+            } else if is_safe_step(f, report[i - 1], report[i + 1]) {
+                Some(i)
+            } else if is_safe_step(f, report[i], report[i + 2]) {
+                Some(i + 1)
+            } else {
+                None
+            }
+        }
+        [(i, (left, _)), (j, (_, right))] => {
+            (i + 1 == j && is_safe_step(f, left, right)).then_some(j)
+        }
+        _ => None,
+    }
+}
+
+fn diagnose(report: &[u32]) -> Diagnosis {
+    let increasing = find_failures(report, &u32::lt);
+    let decreasing = find_failures(report, &u32::gt);
+    if increasing.is_empty() || decreasing.is_empty() {
+        return Diagnosis::Safe;
+    }
+
+    if let Some(level) = find_removal(report, &u32::lt, &increasing)
+        .or_else(|| find_removal(report, &u32::gt, &decreasing))
+    {
+        return Diagnosis::SafeWithoutLevel { level };
+    }
+
+    // Blame the first failure in whichever direction the report mostly follows
+    let is_increasing = increasing.len() <= decreasing.len();
+    let (step, (left, right)) = if is_increasing {
+        increasing[0]
+    } else {
+        decreasing[0]
+    };
+    if left == right {
+        Diagnosis::StepTooSmall { step }
+    } else if (left < right) != is_increasing {
+        Diagnosis::DirectionChange { step }
+    } else {
+        Diagnosis::StepTooLarge { step }
+    }
+}
+
+fn diagnose_all(reports: &[Vec<u32>]) -> Vec<ReportDiagnosis<'_>> {
+    reports
+        .iter()
+        .map(|report| ReportDiagnosis {
+            report,
+            diagnosis: diagnose(report),
+        })
+        .collect()
+}
 
 fn part1(reports: &[Vec<u32>]) -> u32 {
     fn all_adjacent<F>(report: &[u32], f: F) -> bool
@@ -24,35 +167,9 @@ fn part1(reports: &[Vec<u32>]) -> u32 {
 }
 
 fn part2(reports: &[Vec<u32>]) -> u32 {
-    fn all_but_one_adjacent<F>(report: &[u32], f: F) -> bool
-    where
-        F: Fn(&u32, &u32) -> bool,
-    {
-        let is_safe = |left, right| f(&left, &right) && left.abs_diff(right) <= 3;
-
-        let failures = report
-            .iter()
-            .tuple_windows()
-            .enumerate()
-            .filter(|(_, (&left, &right))| !is_safe(left, right))
-            .collect::<Vec<_>>();
-
-        match failures[..] {
-            [] => true,
-            [(i, _)] => {
-                i == 0
-                    || i == report.len() - 2
-                    || is_safe(report[i - 1], report[i + 1])
-                    || is_safe(report[i], report[i + 2])
-            }
-            [(i, (&left, _)), (j, (_, &right))] => i + 1 == j && is_safe(left, right),
-            _ => false,
-        }
-    }
-
     reports
         .iter()
-        .filter(|r| all_but_one_adjacent(r, u32::lt) || all_but_one_adjacent(r, u32::gt))
+        .filter(|r| diagnose(r).is_safe_with_dampener())
         .count() as u32
 }
 
@@ -70,6 +187,51 @@ fn main() {
         })
         .collect::<Vec<_>>();
 
-    println!("Part 1: {}", part1(&reports));
-    println!("Part 2: {}", part2(&reports));
+    match env::args().nth(1).as_deref() {
+        Some("--list") => {
+            for (i, diagnosis) in diagnose_all(&reports).iter().enumerate() {
+                println!("{:>4}: {diagnosis}", i + 1);
+            }
+        }
+        Some("--json") => {
+            let diagnoses = diagnose_all(&reports);
+            println!("{}", serde_json::to_string_pretty(&diagnoses).unwrap());
+        }
+        _ => {
+            println!("Part 1: {}", part1(&reports));
+            println!("Part 2: {}", part2(&reports));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagnose() {
+        assert_eq!(diagnose(&[7, 6, 4, 2, 1]), Diagnosis::Safe);
+        assert_eq!(
+            diagnose(&[1, 2, 7, 8, 9]),
+            Diagnosis::StepTooLarge { step: 1 }
+        );
+        assert_eq!(
+            diagnose(&[9, 7, 6, 2, 1]),
+            Diagnosis::StepTooLarge { step: 2 }
+        );
+        assert_eq!(
+            diagnose(&[1, 3, 2, 4, 5]),
+            Diagnosis::SafeWithoutLevel { level: 1 }
+        );
+        assert_eq!(
+            diagnose(&[8, 6, 4, 4, 1]),
+            Diagnosis::SafeWithoutLevel { level: 2 }
+        );
+        assert_eq!(diagnose(&[1, 3, 6, 7, 9]), Diagnosis::Safe);
+        assert_eq!(
+            diagnose(&[1, 2, 1, 2, 1]),
+            Diagnosis::DirectionChange { step: 1 }
+        );
+        assert_eq!(diagnose(&[5, 5, 5, 5]), Diagnosis::StepTooSmall { step: 0 });
+    }
 }
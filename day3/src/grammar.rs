@@ -0,0 +1,92 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    ops::RangeInclusive,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Opcode {
+    Mul,
+    Add,
+    Do,
+    Dont,
+}
+
+impl Opcode {
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            Opcode::Mul => "mul",
+            Opcode::Add => "add",
+            Opcode::Do => "do",
+            Opcode::Dont => "don't",
+        }
+    }
+
+    pub fn arity(&self) -> usize {
+        match self {
+            Opcode::Mul | Opcode::Add => 2,
+            Opcode::Do | Opcode::Dont => 0,
+        }
+    }
+}
+
+// Widest operand that always fits in the u64 the lexer parses operands into
+pub const MAX_OPERAND_DIGITS: usize = 19;
+
+// The set of recognized instructions and the accepted width of their operands.
+// Opcodes are tried in order, so earlier ones take priority.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grammar {
+    pub(crate) opcodes: Vec<Opcode>,
+    pub(crate) operand_digits: RangeInclusive<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperandDigitsError {
+    pub operand_digits: RangeInclusive<usize>,
+}
+
+impl Display for OperandDigitsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Operand digits {:?} must lie within 1..={MAX_OPERAND_DIGITS} and not be empty",
+            self.operand_digits
+        )
+    }
+}
+
+impl Error for OperandDigitsError {}
+
+impl Grammar {
+    pub fn new(
+        opcodes: Vec<Opcode>,
+        operand_digits: RangeInclusive<usize>,
+    ) -> Result<Self, OperandDigitsError> {
+        let (start, end) = (*operand_digits.start(), *operand_digits.end());
+        if !(1 <= start && start <= end && end <= MAX_OPERAND_DIGITS) {
+            return Err(OperandDigitsError { operand_digits });
+        }
+        Ok(Self {
+            opcodes,
+            operand_digits,
+        })
+    }
+
+    pub fn opcodes(&self) -> &[Opcode] {
+        &self.opcodes
+    }
+
+    pub fn operand_digits(&self) -> &RangeInclusive<usize> {
+        &self.operand_digits
+    }
+}
+
+impl Default for Grammar {
+    fn default() -> Self {
+        Self {
+            opcodes: vec![Opcode::Mul, Opcode::Do, Opcode::Dont],
+            operand_digits: 1..=3,
+        }
+    }
+}
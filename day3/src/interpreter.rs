@@ -0,0 +1,97 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+};
+
+use crate::Instruction;
+
+pub trait Accumulator: Copy + Default + Display {
+    fn from_operand(operand: u64) -> Option<Self>;
+    fn checked_add(self, other: Self) -> Option<Self>;
+    fn checked_mul(self, other: Self) -> Option<Self>;
+}
+
+macro_rules! impl_accumulator {
+    ($($t:ty),*) => {
+        $(
+            impl Accumulator for $t {
+                fn from_operand(operand: u64) -> Option<Self> {
+                    operand.try_into().ok()
+                }
+
+                fn checked_add(self, other: Self) -> Option<Self> {
+                    <$t>::checked_add(self, other)
+                }
+
+                fn checked_mul(self, other: Self) -> Option<Self> {
+                    <$t>::checked_mul(self, other)
+                }
+            }
+        )*
+    };
+}
+
+impl_accumulator!(u32, u64, u128);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OverflowError {
+    pub instruction: Instruction,
+}
+
+impl Display for OverflowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Accumulator overflowed executing {}", self.instruction)
+    }
+}
+
+impl Error for OverflowError {}
+
+pub struct Interpreter<A = u32> {
+    enabled: bool,
+    use_toggles: bool,
+    total: A,
+}
+
+impl<A: Accumulator> Interpreter<A> {
+    pub fn new(use_toggles: bool) -> Self {
+        Self {
+            enabled: true,
+            use_toggles,
+            total: A::default(),
+        }
+    }
+
+    // Leaves the total unchanged if the instruction would overflow it
+    pub fn execute(&mut self, instruction: Instruction) -> Result<(), OverflowError> {
+        let (a, b, operation): (_, _, fn(A, A) -> Option<A>) = match instruction {
+            Instruction::Mul(a, b) => (a, b, A::checked_mul),
+            Instruction::Add(a, b) => (a, b, A::checked_add),
+            Instruction::Do => {
+                self.enabled = true;
+                return Ok(());
+            }
+            Instruction::Dont => {
+                self.enabled = false;
+                return Ok(());
+            }
+        };
+        if !self.is_enabled() {
+            return Ok(());
+        }
+
+        self.total = A::from_operand(a)
+            .zip(A::from_operand(b))
+            .and_then(|(a, b)| operation(a, b))
+            .and_then(|value| self.total.checked_add(value))
+            .ok_or(OverflowError { instruction })?;
+        Ok(())
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled || !self.use_toggles
+    }
+
+    pub fn total(&self) -> A {
+        self.total
+    }
+}
@@ -0,0 +1,197 @@
+use std::{
+    fmt::{self, Display},
+    ops::Range,
+};
+
+use crate::{Grammar, Opcode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    Mul(u64, u64),
+    Add(u64, u64),
+    Do,
+    Dont,
+}
+
+impl Instruction {
+    fn build(opcode: Opcode, operands: &[u64]) -> Option<Self> {
+        let instruction = match (opcode, operands) {
+            (Opcode::Mul, &[a, b]) => Instruction::Mul(a, b),
+            (Opcode::Add, &[a, b]) => Instruction::Add(a, b),
+            (Opcode::Do, []) => Instruction::Do,
+            (Opcode::Dont, []) => Instruction::Dont,
+            _ => return None,
+        };
+        Some(instruction)
+    }
+
+    pub fn opcode(&self) -> Opcode {
+        match self {
+            Instruction::Mul(..) => Opcode::Mul,
+            Instruction::Add(..) => Opcode::Add,
+            Instruction::Do => Opcode::Do,
+            Instruction::Dont => Opcode::Dont,
+        }
+    }
+
+    pub fn operands(&self) -> Vec<u64> {
+        match *self {
+            Instruction::Mul(a, b) | Instruction::Add(a, b) => vec![a, b],
+            Instruction::Do | Instruction::Dont => vec![],
+        }
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let operands = self
+            .operands()
+            .iter()
+            .map(u64::to_string)
+            .collect::<Vec<_>>();
+        write!(f, "{}({})", self.opcode().keyword(), operands.join(","))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub instruction: Instruction,
+    pub span: Range<usize>,
+}
+
+// Outcome of trying to lex an instruction at the start of some input
+pub(crate) enum Scan {
+    Match(Instruction, usize),
+    Mismatch,
+    // The input ended before the instruction could be accepted or rejected
+    Incomplete,
+}
+
+fn expect(input: &[u8], position: usize, literal: &[u8]) -> Result<usize, Scan> {
+    for (i, &expected) in literal.iter().enumerate() {
+        match input.get(position + i) {
+            None => return Err(Scan::Incomplete),
+            Some(&b) if b != expected => return Err(Scan::Mismatch),
+            Some(_) => {}
+        }
+    }
+    Ok(position + literal.len())
+}
+
+// Operands are numbers with a digit count in the grammar's range; returns them
+// with the position just past the closing parenthesis. A list longer than the
+// opcode's arity is rejected as soon as it is seen, so a stream never has to
+// hold an unterminated list.
+fn scan_operands(
+    input: &[u8],
+    mut position: usize,
+    grammar: &Grammar,
+    arity: usize,
+) -> Result<(Vec<u64>, usize), Scan> {
+    let mut operands = Vec::new();
+    match input.get(position) {
+        None => return Err(Scan::Incomplete),
+        Some(b')') => return Ok((operands, position + 1)),
+        Some(_) => {}
+    }
+
+    loop {
+        if operands.len() == arity {
+            return Err(Scan::Mismatch);
+        }
+        let digits = input[position..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        if digits > *grammar.operand_digits.end() {
+            return Err(Scan::Mismatch);
+        }
+        if position + digits == input.len() {
+            return Err(Scan::Incomplete);
+        }
+        if !grammar.operand_digits.contains(&digits) {
+            return Err(Scan::Mismatch);
+        }
+        let operand = input[position..(position + digits)]
+            .iter()
+            .try_fold(0u64, |acc, b| {
+                acc.checked_mul(10)?.checked_add((b - b'0') as u64)
+            })
+            .ok_or(Scan::Mismatch)?;
+        operands.push(operand);
+        position += digits;
+
+        match input.get(position) {
+            None => return Err(Scan::Incomplete),
+            Some(b',') => position += 1,
+            Some(b')') => return Ok((operands, position + 1)),
+            Some(_) => return Err(Scan::Mismatch),
+        }
+    }
+}
+
+// Every keyword is followed by a parenthesized, comma-separated operand list
+fn scan_opcode(
+    input: &[u8],
+    opcode: Opcode,
+    grammar: &Grammar,
+) -> Result<(Instruction, usize), Scan> {
+    let position = expect(input, 0, opcode.keyword().as_bytes())?;
+    let position = expect(input, position, b"(")?;
+    let (operands, len) = scan_operands(input, position, grammar, opcode.arity())?;
+    let instruction = Instruction::build(opcode, &operands).ok_or(Scan::Mismatch)?;
+    Ok((instruction, len))
+}
+
+// An earlier opcode that might still match takes priority over a later one
+// that already does.
+pub(crate) fn scan(input: &[u8], grammar: &Grammar) -> Scan {
+    for &opcode in &grammar.opcodes {
+        match scan_opcode(input, opcode, grammar) {
+            Ok((instruction, len)) => return Scan::Match(instruction, len),
+            Err(Scan::Mismatch) => {}
+            Err(scan) => return scan,
+        }
+    }
+    Scan::Mismatch
+}
+
+pub struct Lexer<'a> {
+    input: &'a [u8],
+    grammar: Grammar,
+    position: usize,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self::with_grammar(input, Grammar::default())
+    }
+
+    pub fn with_grammar(input: &'a str, grammar: Grammar) -> Self {
+        Self {
+            input: input.as_bytes(),
+            grammar,
+            position: 0,
+        }
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.position < self.input.len() {
+            let start = self.position;
+            // The whole input is available, so an incomplete instruction never completes
+            if let Scan::Match(instruction, len) = scan(&self.input[start..], &self.grammar) {
+                self.position += len;
+                return Some(Token {
+                    instruction,
+                    span: start..self.position,
+                });
+            }
+            self.position += 1;
+        }
+        None
+    }
+}
@@ -0,0 +1,203 @@
+mod grammar;
+mod interpreter;
+mod lexer;
+mod listing;
+mod stream;
+
+#[cfg(feature = "regex")]
+pub mod reference;
+
+pub use grammar::{Grammar, Opcode, OperandDigitsError, MAX_OPERAND_DIGITS};
+pub use interpreter::{Accumulator, Interpreter, OverflowError};
+pub use lexer::{Instruction, Lexer, Token};
+pub use listing::{annotate, highlight, table, Annotation};
+pub use stream::{interpret_stream, StreamLexer};
+
+pub fn interpret<A: Accumulator>(
+    input: &str,
+    grammar: &Grammar,
+    use_toggles: bool,
+) -> Result<A, OverflowError> {
+    let mut interpreter = Interpreter::new(use_toggles);
+    for token in Lexer::with_grammar(input, grammar.clone()) {
+        interpreter.execute(token.instruction)?;
+    }
+    Ok(interpreter.total())
+}
+
+pub fn part1(input: &str) -> u32 {
+    interpret(input, &Grammar::default(), false).unwrap()
+}
+
+pub fn part2(input: &str) -> u32 {
+    interpret(input, &Grammar::default(), true).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lexer() {
+        let input = "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
+        let tokens = Lexer::new(input).collect::<Vec<_>>();
+        let expected = [
+            (Instruction::Mul(2, 4), 1..9),
+            (Instruction::Dont, 20..27),
+            (Instruction::Mul(5, 5), 28..36),
+            (Instruction::Mul(11, 8), 48..57),
+            (Instruction::Do, 59..63),
+            (Instruction::Mul(8, 5), 64..72),
+        ]
+        .map(|(instruction, span)| Token { instruction, span });
+        assert_eq!(tokens, expected);
+    }
+
+    #[test]
+    fn test_lexer_rejects_malformed_operands() {
+        for input in [
+            "mul(1234,5)",
+            "mul(,5)",
+            "mul(1,2,3)",
+            "mul(1 ,2)",
+            "do(1)",
+            "mul(4*",
+        ] {
+            assert_eq!(Lexer::new(input).next(), None, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_examples() {
+        assert_eq!(
+            part1("xmul(2,4)%&mul[3,7]!@^do_not_mul(5,5)+mul(32,64]then(mul(11,8)mul(8,5))"),
+            161
+        );
+        assert_eq!(
+            part2("xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))"),
+            48
+        );
+    }
+
+    #[test]
+    fn test_annotate() {
+        let input = "mul(2,4)don't()mul(5,5)do()mul(8,5)";
+        let annotations = annotate(input);
+        let summary = annotations
+            .iter()
+            .map(|a| (a.token.span.start, a.enabled, a.total))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                (0, true, 8),
+                (8, true, 8),
+                (15, false, 8),
+                (23, false, 8),
+                (27, true, 48)
+            ]
+        );
+        assert_eq!(
+            highlight(input, &annotations),
+            "\x1b[1;32mmul(2,4)\x1b[0m\x1b[1;31mdon't()\x1b[0m\x1b[2mmul(5,5)\x1b[0m\
+             \x1b[1;36mdo()\x1b[0m\x1b[1;32mmul(8,5)\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_stream_lexer_across_chunks() {
+        let input = "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
+        let expected = Lexer::new(input).collect::<Vec<_>>();
+        for chunk_size in 1..=input.len() {
+            let tokens = StreamLexer::with_chunk_size(input.as_bytes(), chunk_size)
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            assert_eq!(tokens, expected, "chunk size {chunk_size}");
+        }
+    }
+
+    #[test]
+    fn test_stream_lexer_unterminated_operands() {
+        struct Failing;
+
+        impl std::io::Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("read failed"))
+            }
+        }
+
+        // An operand list that never closes is dropped rather than buffered
+        let input = format!("mul(1{}", ",1".repeat(10_000));
+        let reader = std::io::Read::chain(input.as_bytes(), Failing);
+        let mut lexer = StreamLexer::with_chunk_size(reader, 16);
+        assert!(lexer.next().unwrap().is_err());
+        assert!(lexer.buffered() <= 16);
+    }
+
+    #[test]
+    fn test_interpret_stream() {
+        let input = std::fs::read_to_string("data/input.txt").unwrap();
+        assert_eq!(
+            interpret_stream::<u32, _>(input.as_bytes(), &Grammar::default()).unwrap(),
+            (part1(&input), part2(&input))
+        );
+    }
+
+    #[test]
+    fn test_extended_grammar() {
+        let grammar = Grammar::new(
+            vec![Opcode::Mul, Opcode::Add, Opcode::Do, Opcode::Dont],
+            1..=5,
+        )
+        .unwrap();
+        let input = "mul(12345,2)add(1,2)don't()add(3,4)do()mul(1234,56789)";
+        let tokens = Lexer::with_grammar(input, grammar.clone())
+            .map(|t| t.instruction)
+            .collect::<Vec<_>>();
+        assert_eq!(tokens[1], Instruction::Add(1, 2));
+        assert_eq!(tokens[5], Instruction::Mul(1234, 56789));
+        assert_eq!(
+            interpret::<u64>(input, &grammar, true),
+            Ok(24690 + 3 + 70077626)
+        );
+        assert_eq!(
+            interpret::<u64>(input, &grammar, false),
+            Ok(24690 + 3 + 7 + 70077626)
+        );
+        assert_eq!(Lexer::new(input).count(), 2);
+    }
+
+    #[test]
+    fn test_accumulator_overflow() {
+        let grammar = Grammar::new(Grammar::default().opcodes().to_vec(), 1..=10).unwrap();
+        let input = "mul(4294967295,2)";
+        assert_eq!(
+            interpret::<u32>(input, &grammar, false),
+            Err(OverflowError {
+                instruction: Instruction::Mul(4294967295, 2)
+            })
+        );
+        assert_eq!(interpret::<u64>(input, &grammar, false), Ok(8589934590));
+        assert_eq!(interpret::<u128>(input, &grammar, false), Ok(8589934590));
+
+        // The widest operands the grammar allows still reach the accumulator
+        let grammar = Grammar::new(vec![Opcode::Mul], 1..=MAX_OPERAND_DIGITS).unwrap();
+        let input = "mul(9999999999999999999,10)";
+        assert_eq!(
+            interpret::<u128>(input, &grammar, false),
+            Ok(99999999999999999990)
+        );
+        for (start, end) in [(0, 3), (3, 1), (1, MAX_OPERAND_DIGITS + 1)] {
+            let operand_digits = std::ops::RangeInclusive::new(start, end);
+            assert!(Grammar::new(vec![Opcode::Mul], operand_digits).is_err());
+        }
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_matches_reference() {
+        let input = std::fs::read_to_string("data/input.txt").unwrap();
+        assert_eq!(part1(&input), reference::part1(&input));
+        assert_eq!(part2(&input), reference::part2(&input));
+    }
+}
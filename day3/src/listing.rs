@@ -0,0 +1,94 @@
+use std::fmt::Write;
+
+use crate::{Instruction, Interpreter, Lexer, Token};
+
+const RESET: &str = "\x1b[0m";
+const ENABLED: &str = "\x1b[1;32m";
+const DISABLED: &str = "\x1b[2m";
+const DO: &str = "\x1b[1;36m";
+const DONT: &str = "\x1b[1;31m";
+
+pub struct Annotation {
+    pub token: Token,
+    pub enabled: bool,
+    pub total: u64,
+}
+
+// Runs the input with toggles, recording the enable state and running total
+// after every recognized instruction.
+pub fn annotate(input: &str) -> Vec<Annotation> {
+    let mut interpreter = Interpreter::<u64>::new(true);
+    Lexer::new(input)
+        .map(|token| {
+            let enabled = interpreter.is_enabled();
+            interpreter.execute(token.instruction).unwrap();
+            Annotation {
+                token,
+                enabled,
+                total: interpreter.total(),
+            }
+        })
+        .collect()
+}
+
+pub fn highlight(input: &str, annotations: &[Annotation]) -> String {
+    let mut result = String::with_capacity(input.len());
+    let mut position = 0;
+    for Annotation { token, enabled, .. } in annotations {
+        let color = match token.instruction {
+            Instruction::Mul(..) | Instruction::Add(..) if *enabled => ENABLED,
+            Instruction::Mul(..) | Instruction::Add(..) => DISABLED,
+            Instruction::Do => DO,
+            Instruction::Dont => DONT,
+        };
+        result.push_str(&input[position..token.span.start]);
+        result.push_str(color);
+        result.push_str(&input[token.span.clone()]);
+        result.push_str(RESET);
+        position = token.span.end;
+    }
+    result.push_str(&input[position..]);
+    result
+}
+
+pub fn table(annotations: &[Annotation]) -> String {
+    let mut result = format!(
+        "{:>8}  {:<12}  {:<8}  {:>8}  {:>10}\n",
+        "offset", "instruction", "operands", "enabled", "total"
+    );
+    for Annotation {
+        token,
+        enabled,
+        total,
+    } in annotations
+    {
+        let operands = token
+            .instruction
+            .operands()
+            .iter()
+            .map(u64::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        let enabled = match token.instruction {
+            Instruction::Mul(..) | Instruction::Add(..) => {
+                if *enabled {
+                    "yes"
+                } else {
+                    "no"
+                }
+            }
+            Instruction::Do | Instruction::Dont => "",
+        };
+        writeln!(
+            result,
+            "{:>8}  {:<12}  {:<8}  {:>8}  {:>10}",
+            token.span.start,
+            token.instruction.to_string(),
+            operands,
+            enabled,
+            total
+        )
+        .unwrap();
+    }
+    result
+}
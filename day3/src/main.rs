@@ -1,49 +1,21 @@
-use std::fs;
+use std::{env, fs, fs::File, io};
 
-use regex::Regex;
-
-fn part1(input: &str) -> u32 {
-    Regex::new(r"mul\((\d{1,3}),(\d{1,3})\)")
-        .unwrap()
-        .captures_iter(input)
-        .map(|c| {
-            let (_, [a, b]) = c.extract();
-            let a = a.parse::<u32>().unwrap();
-            let b = b.parse::<u32>().unwrap();
-            a * b
-        })
-        .sum()
-}
-
-fn part2(input: &str) -> u32 {
-    Regex::new(r"don't\(\)|do\(\)|mul\((\d{1,3}),(\d{1,3})\)")
-        .unwrap()
-        .captures_iter(input)
-        .scan(true, |state, c| match c.get(0).unwrap().as_str() {
-            "do()" => {
-                *state = true;
-                Some(0)
-            }
-            "don't()" => {
-                *state = false;
-                Some(0)
-            }
-            _ => Some(
-                state
-                    .then(|| {
-                        let a = c.get(1).unwrap().as_str().parse::<u32>().unwrap();
-                        let b = c.get(2).unwrap().as_str().parse::<u32>().unwrap();
-                        a * b
-                    })
-                    .unwrap_or_default(),
-            ),
-        })
-        .sum()
-}
+use day3::{annotate, highlight, interpret_stream, table, Grammar};
 
 fn main() {
-    let input = fs::read_to_string("day3/data/input.txt").unwrap();
+    let grammar = Grammar::default();
+    let (part1, part2): (u64, u64) = match env::args().nth(1).as_deref() {
+        Some("--listing") => {
+            let input = fs::read_to_string("day3/data/input.txt").unwrap();
+            let annotations = annotate(&input);
+            println!("{}", highlight(&input, &annotations));
+            print!("{}", table(&annotations));
+            return;
+        }
+        Some("-") => interpret_stream(io::stdin().lock(), &grammar).unwrap(),
+        _ => interpret_stream(File::open("day3/data/input.txt").unwrap(), &grammar).unwrap(),
+    };
 
-    println!("Part 1: {}", part1(&input));
-    println!("Part 2: {}", part2(&input));
+    println!("Part 1: {part1}");
+    println!("Part 2: {part2}");
 }
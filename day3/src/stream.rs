@@ -0,0 +1,118 @@
+use std::io::{self, ErrorKind, Read};
+
+use crate::{
+    lexer::{scan, Scan},
+    Accumulator, Grammar, Interpreter, Token,
+};
+
+const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+// Lexes instructions from a reader, holding at most one chunk plus the start of
+// an instruction that straddles the chunk boundary in memory.
+pub struct StreamLexer<R> {
+    reader: R,
+    grammar: Grammar,
+    chunk_size: usize,
+    buffer: Vec<u8>,
+    // Position within the buffer, and absolute offset of the buffer's first byte
+    position: usize,
+    offset: usize,
+    eof: bool,
+}
+
+impl<R: Read> StreamLexer<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_chunk_size(reader, DEFAULT_CHUNK_SIZE)
+    }
+
+    pub fn with_chunk_size(reader: R, chunk_size: usize) -> Self {
+        Self::with_grammar(reader, Grammar::default(), chunk_size)
+    }
+
+    pub fn with_grammar(reader: R, grammar: Grammar, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "Chunk size must be positive");
+        Self {
+            reader,
+            grammar,
+            chunk_size,
+            buffer: Vec::new(),
+            position: 0,
+            offset: 0,
+            eof: false,
+        }
+    }
+
+    // Bytes held in memory, read but not yet lexed past
+    #[cfg(test)]
+    pub(crate) fn buffered(&self) -> usize {
+        self.buffer.len() - self.position
+    }
+
+    fn fill(&mut self) -> io::Result<()> {
+        self.buffer.drain(..self.position);
+        self.offset += self.position;
+        self.position = 0;
+
+        let len = self.buffer.len();
+        self.buffer.resize(len + self.chunk_size, 0);
+        let read = loop {
+            match self.reader.read(&mut self.buffer[len..]) {
+                Ok(read) => break read,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => {
+                    self.buffer.truncate(len);
+                    return Err(e);
+                }
+            }
+        };
+        self.buffer.truncate(len + read);
+        self.eof = read == 0;
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for StreamLexer<R> {
+    type Item = io::Result<Token>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            while self.position < self.buffer.len() {
+                let start = self.position;
+                match scan(&self.buffer[start..], &self.grammar) {
+                    Scan::Match(instruction, len) => {
+                        self.position += len;
+                        let span = (self.offset + start)..(self.offset + self.position);
+                        return Some(Ok(Token { instruction, span }));
+                    }
+                    Scan::Incomplete if !self.eof => break,
+                    Scan::Incomplete | Scan::Mismatch => self.position += 1,
+                }
+            }
+
+            if self.eof && self.position == self.buffer.len() {
+                return None;
+            }
+            if let Err(e) = self.fill() {
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+// Computes both parts in a single pass over the reader
+pub fn interpret_stream<A: Accumulator, R: Read>(
+    reader: R,
+    grammar: &Grammar,
+) -> io::Result<(A, A)> {
+    let mut without_toggles = Interpreter::new(false);
+    let mut with_toggles = Interpreter::new(true);
+    let lexer = StreamLexer::with_grammar(reader, grammar.clone(), DEFAULT_CHUNK_SIZE);
+    for token in lexer {
+        let instruction = token?.instruction;
+        without_toggles
+            .execute(instruction)
+            .and_then(|_| with_toggles.execute(instruction))
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+    }
+    Ok((without_toggles.total(), with_toggles.total()))
+}
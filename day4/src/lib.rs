@@ -0,0 +1,226 @@
+mod template;
+mod word_search;
+
+use std::collections::HashSet;
+
+use anyhow::bail;
+use ndarray::Array2;
+
+pub use template::{Placement, Template, TemplateMatcher};
+pub use word_search::{Direction, WordMatch, WordSearch};
+
+// Builds a grid from lines of text, which must all have the same number of
+// characters. Empty input gives an empty grid.
+pub fn parse_grid(input: &str) -> anyhow::Result<Array2<char>> {
+    let rows = input
+        .lines()
+        .map(|line| line.chars().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let num_rows = rows.len();
+    let num_cols = rows.first().map_or(0, Vec::len);
+    if let Some(row) = rows.iter().position(|row| row.len() != num_cols) {
+        bail!(
+            "Grid is not rectangular: row {row} has {} characters but row 0 has {num_cols}",
+            rows[row].len()
+        );
+    }
+    let grid = Array2::from_shape_vec((num_rows, num_cols), rows.concat())?;
+    Ok(grid)
+}
+
+pub fn xmas_matches(grid: &Array2<char>) -> Vec<WordMatch> {
+    WordSearch::new(["XMAS"]).find(grid)
+}
+
+pub fn x_mas_matcher() -> TemplateMatcher {
+    TemplateMatcher::new(Template::build("M.S/.A./M.S").unwrap(), true)
+}
+
+// Center cells of every X-shaped pair of MAS
+pub fn x_mas_centers(grid: &Array2<char>) -> Vec<(usize, usize)> {
+    x_mas_matcher()
+        .find(grid)
+        .into_iter()
+        .map(|placement| (placement.row + 1, placement.col + 1))
+        .collect()
+}
+
+// Renders the grid with every cell outside the given set replaced by '.'
+pub fn render<I>(grid: &Array2<char>, cells: I) -> String
+where
+    I: IntoIterator<Item = (usize, usize)>,
+{
+    let cells = cells.into_iter().collect::<HashSet<_>>();
+    let mut result = String::new();
+    for (r, row) in grid.rows().into_iter().enumerate() {
+        for (c, &ch) in row.iter().enumerate() {
+            result.push(if cells.contains(&(r, c)) { ch } else { '.' });
+        }
+        result.push('\n');
+    }
+    result
+}
+
+pub fn part1(grid: &Array2<char>) -> u32 {
+    xmas_matches(grid).len() as u32
+}
+
+pub fn part2(grid: &Array2<char>) -> u32 {
+    x_mas_centers(grid).len() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> Array2<char> {
+        parse_grid(include_str!("../data/example.txt")).unwrap()
+    }
+
+    #[test]
+    fn test_example() {
+        assert_eq!(part1(&example()), 18);
+        assert_eq!(part2(&example()), 9);
+    }
+
+    #[test]
+    fn test_word_search() {
+        let search = WordSearch::new(["XMAS", "MAS", "SAM", "ZZZ"]);
+        let counts = search.counts(&example());
+        assert_eq!(counts[0], 18);
+        // MAS and SAM are reverses of each other, so they occur equally often
+        assert_eq!(counts[1], counts[2]);
+        assert_eq!(counts[3], 0);
+    }
+
+    #[test]
+    fn test_match_locations() {
+        let grid = Array2::from_shape_vec((4, 4), "XMAS.M....A....S".chars().collect()).unwrap();
+        let mut matches = xmas_matches(&grid)
+            .into_iter()
+            .map(|m| (m.row, m.col, m.direction))
+            .collect::<Vec<_>>();
+        matches.sort_by_key(|&(r, c, _)| (r, c));
+        assert_eq!(
+            matches,
+            [(0, 0, Direction::East), (0, 0, Direction::SouthEast)]
+        );
+    }
+
+    #[test]
+    fn test_template_symmetries() {
+        let template = Template::build("M.S/.A./M.S").unwrap();
+        assert_eq!(template.symmetries().len(), 4);
+        let template = Template::build("AB/C.").unwrap();
+        assert_eq!(template.symmetries().len(), 8);
+        assert_eq!(template.rotated(), Template::build("CA/.B").unwrap());
+        assert_eq!(template.reflected(), Template::build("BA/.C").unwrap());
+        assert!(Template::build("AB/C").is_err());
+    }
+
+    #[test]
+    fn test_template_matcher() {
+        let grid = example();
+        let matcher = TemplateMatcher::new(Template::build("XMAS").unwrap(), true);
+        // Rotations and reflections cover the horizontal and vertical directions
+        let search = WordSearch::new(["XMAS"]);
+        let axis_count = search
+            .find(&grid)
+            .iter()
+            .filter(|m| {
+                let (dr, dc) = m.direction.delta();
+                dr == 0 || dc == 0
+            })
+            .count();
+        assert_eq!(matcher.count(&grid), axis_count);
+
+        let matcher = TemplateMatcher::new(Template::build("M.S/.A./M.S").unwrap(), false);
+        assert_eq!(matcher.variants().len(), 1);
+        // Only the X-MAS centers at (1, 2) and (3, 2) have both Ms on the left
+        assert_eq!(matcher.count(&grid), 2);
+    }
+
+    #[test]
+    fn test_render() {
+        let grid = example();
+        let search = WordSearch::new(["XMAS"]);
+        let cells = search
+            .find(&grid)
+            .iter()
+            .flat_map(|m| search.match_cells(m).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let expected = "\
+....XXMAS.
+.SAMXMS...
+...S..A...
+..A.A.MS.X
+XMASAMX.MM
+X.....XA.A
+S.S.S.S.SS
+.A.A.A.A.A
+..M.M.M.MM
+.X.X.XMASX
+";
+        assert_eq!(render(&grid, cells), expected);
+    }
+
+    #[test]
+    fn test_empty_grid() {
+        let grid = parse_grid("").unwrap();
+        assert_eq!(grid.dim(), (0, 0));
+        assert_eq!(part1(&grid), 0);
+        assert_eq!(part2(&grid), 0);
+        assert_eq!(render(&grid, []), "");
+    }
+
+    #[test]
+    fn test_single_row_grid() {
+        let grid = parse_grid("XMASAMX").unwrap();
+        assert_eq!(grid.dim(), (1, 7));
+        assert_eq!(part1(&grid), 2);
+        assert_eq!(part2(&grid), 0);
+    }
+
+    #[test]
+    fn test_single_column_grid() {
+        let grid = parse_grid("X\nM\nA\nS\nA\nM\nX\n").unwrap();
+        assert_eq!(grid.dim(), (7, 1));
+        assert_eq!(part1(&grid), 2);
+        assert_eq!(part2(&grid), 0);
+    }
+
+    #[test]
+    fn test_single_cell_grid() {
+        let grid = parse_grid("X").unwrap();
+        assert_eq!(part1(&grid), 0);
+        assert_eq!(part2(&grid), 0);
+        // A one-letter word reads the same in all 8 directions
+        assert_eq!(WordSearch::new(["X"]).count(&grid), 8);
+    }
+
+    #[test]
+    fn test_unicode_grid() {
+        let grid = parse_grid("ÄXMAS\nÖÖMÖÖ\nÖÖÖAÖ\nÖÖÖÖS").unwrap();
+        assert_eq!(grid.dim(), (4, 5));
+        let matches = xmas_matches(&grid)
+            .into_iter()
+            .map(|m| (m.row, m.col, m.direction))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            matches,
+            [(0, 1, Direction::East), (0, 1, Direction::SouthEast)]
+        );
+        assert_eq!(WordSearch::new(["ÄX"]).count(&grid), 1);
+    }
+
+    #[test]
+    fn test_ragged_grid() {
+        let error = parse_grid("XMAS\nXMA\nXMAS").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Grid is not rectangular: row 1 has 3 characters but row 0 has 4"
+        );
+        // Row length is counted in characters, not bytes
+        assert!(parse_grid("ÄÖ\nAO").is_ok());
+    }
+}
@@ -1,109 +1,41 @@
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-};
-
-use itertools::chain;
+use std::{env, fs};
 
-use ndarray::Array2;
+use day4::{
+    parse_grid, part1, part2, render, x_mas_centers, x_mas_matcher, xmas_matches, WordSearch,
+};
 
-fn part1(grid: &Array2<char>) -> u32 {
-    let (num_rows, num_cols) = grid.dim();
+fn main() {
+    let input = fs::read_to_string("day4/data/input.txt").unwrap();
+    let grid = parse_grid(&input).unwrap();
 
-    fn find_xmas(state: &mut Option<char>, c: &char) -> Option<u32> {
-        let found_xmas = match c {
-            'X' => {
-                *state = Some('X');
-                false
-            }
-            'M' => {
-                if let Some('X') = state.take() {
-                    *state = Some('M');
-                }
-                false
+    match env::args().nth(1).as_deref() {
+        Some("--matches") => {
+            for m in xmas_matches(&grid) {
+                println!("XMAS at ({}, {}) heading {}", m.row, m.col, m.direction);
             }
-            'A' => {
-                if let Some('M') = state.take() {
-                    *state = Some('A');
-                }
-                false
+            for (r, c) in x_mas_centers(&grid) {
+                println!("X-MAS centered at ({r}, {c})");
             }
-            'S' => {
-                matches!(state.take(), Some('A'))
-            }
-            _ => false,
-        };
-
-        Some(found_xmas as u32)
+        }
+        Some("--render") => {
+            let search = WordSearch::new(["XMAS"]);
+            let cells = search
+                .find(&grid)
+                .iter()
+                .flat_map(|m| search.match_cells(m).collect::<Vec<_>>())
+                .collect::<Vec<_>>();
+            println!("Part 1:\n{}", render(&grid, cells));
+            let matcher = x_mas_matcher();
+            let cells = matcher
+                .find(&grid)
+                .iter()
+                .flat_map(|p| matcher.cells(p).collect::<Vec<_>>())
+                .collect::<Vec<_>>();
+            println!("Part 2:\n{}", render(&grid, cells));
+        }
+        _ => {
+            println!("Part 1: {}", part1(&grid));
+            println!("Part 2: {}", part2(&grid));
+        }
     }
-
-    let axis_count = chain!(grid.rows().into_iter(), grid.columns().into_iter())
-        .map(|view| {
-            let forwards = view.iter().scan(None, find_xmas).sum::<u32>();
-            let backwards = view.iter().rev().scan(None, find_xmas).sum::<u32>();
-            forwards + backwards
-        })
-        .sum::<u32>();
-
-    let diagonal_count = chain!(
-        // Forward diagonals
-        (0..(num_rows + num_cols - 1)).map(|start| {
-            (0..num_rows)
-                .filter_map(|r| {
-                    let c = num_cols as i32 - 1 - start as i32 + r as i32;
-                    ((0..num_cols as i32).contains(&c)).then(|| grid[[r, c as usize]])
-                })
-                .collect::<Vec<_>>()
-        }),
-        // Backward diagonals
-        (0..(num_rows + num_cols - 1)).map(|start| {
-            (0..num_rows)
-                .filter_map(|r| {
-                    let c = start as i32 - r as i32;
-                    ((0..num_cols as i32).contains(&c)).then(|| grid[[r, c as usize]])
-                })
-                .collect::<Vec<_>>()
-        }),
-    )
-    .map(|diag| {
-        let forwards = diag.iter().scan(None, find_xmas).sum::<u32>();
-        let backwards = diag.iter().rev().scan(None, find_xmas).sum::<u32>();
-        forwards + backwards
-    })
-    .sum::<u32>();
-
-    axis_count + diagonal_count
-}
-
-fn part2(grid: &Array2<char>) -> u32 {
-    let (num_rows, num_cols) = grid.dim();
-
-    let is_ms = |a, b| matches!((a, b), ('M', 'S') | ('S', 'M'));
-
-    let is_xmas = |r, c| {
-        grid[[r, c]] == 'A'
-            && is_ms(grid[[r - 1, c - 1]], grid[[r + 1, c + 1]])
-            && is_ms(grid[[r - 1, c + 1]], grid[[r + 1, c - 1]])
-    };
-
-    (1..(num_rows - 1))
-        .map(|r| {
-            (1..(num_cols - 1))
-                .map(|c| is_xmas(r, c) as u32)
-                .sum::<u32>()
-        })
-        .sum::<u32>()
-}
-
-fn main() {
-    let file = File::open("day4/data/input.txt").unwrap();
-    let reader = BufReader::new(file);
-    let rows = reader.lines().map_while(Result::ok).collect::<Vec<_>>();
-    let num_rows = rows.len();
-    let num_cols = rows.first().unwrap().len();
-    let vec = rows.concat().chars().collect::<Vec<_>>();
-    let grid = Array2::<char>::from_shape_vec((num_rows, num_cols), vec).unwrap();
-
-    println!("Part 1: {}", part1(&grid));
-    println!("Part 2: {}", part2(&grid));
 }
@@ -0,0 +1,139 @@
+use anyhow::{bail, ensure};
+use ndarray::Array2;
+
+const WILDCARD: char = '.';
+
+// A small rectangular pattern of letters, where '.' matches any letter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    cells: Array2<Option<char>>,
+}
+
+impl Template {
+    // Rows are separated by '/' or newlines, e.g. "M.S/.A./M.S"
+    pub fn build(s: &str) -> anyhow::Result<Self> {
+        let rows = s
+            .split(['/', '\n'])
+            .map(|row| row.chars().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let num_cols = rows[0].len();
+        ensure!(num_cols > 0, "Template \"{s}\" has an empty row");
+        if let Some(row) = rows.iter().position(|row| row.len() != num_cols) {
+            bail!("Template \"{s}\" is not rectangular: row {row} differs in length from row 0");
+        }
+
+        let vec = rows
+            .concat()
+            .into_iter()
+            .map(|c| (c != WILDCARD).then_some(c))
+            .collect();
+        let cells = Array2::from_shape_vec((rows.len(), num_cols), vec)?;
+        Ok(Self { cells })
+    }
+
+    pub fn dim(&self) -> (usize, usize) {
+        self.cells.dim()
+    }
+
+    // Rotated a quarter turn clockwise
+    pub fn rotated(&self) -> Self {
+        let (num_rows, num_cols) = self.cells.dim();
+        let cells = Array2::from_shape_fn((num_cols, num_rows), |(r, c)| {
+            self.cells[[num_rows - 1 - c, r]]
+        });
+        Self { cells }
+    }
+
+    // Mirrored left to right
+    pub fn reflected(&self) -> Self {
+        let (num_rows, num_cols) = self.cells.dim();
+        let cells = Array2::from_shape_fn((num_rows, num_cols), |(r, c)| {
+            self.cells[[r, num_cols - 1 - c]]
+        });
+        Self { cells }
+    }
+
+    // Every distinct rotation and reflection, starting with this template
+    pub fn symmetries(&self) -> Vec<Self> {
+        let mut result: Vec<Self> = Vec::new();
+        for mut template in [self.clone(), self.reflected()] {
+            for _ in 0..4 {
+                if !result.contains(&template) {
+                    result.push(template.clone());
+                }
+                template = template.rotated();
+            }
+        }
+        result
+    }
+
+    pub fn matches_at(&self, grid: &Array2<char>, row: usize, col: usize) -> bool {
+        self.cells
+            .indexed_iter()
+            .all(|((r, c), cell)| cell.is_none_or(|ch| grid.get([row + r, col + c]) == Some(&ch)))
+    }
+
+    // Grid cells covered by the non-wildcard letters when placed at (row, col)
+    pub fn cells(&self, row: usize, col: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.cells
+            .indexed_iter()
+            .filter_map(move |((r, c), cell)| cell.is_some().then_some((row + r, col + c)))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Placement {
+    pub row: usize,
+    pub col: usize,
+    // Index into the matcher's variants
+    pub variant: usize,
+}
+
+pub struct TemplateMatcher {
+    variants: Vec<Template>,
+}
+
+impl TemplateMatcher {
+    pub fn new(template: Template, with_symmetries: bool) -> Self {
+        let variants = if with_symmetries {
+            template.symmetries()
+        } else {
+            vec![template]
+        };
+        Self { variants }
+    }
+
+    pub fn variants(&self) -> &[Template] {
+        &self.variants
+    }
+
+    pub fn find(&self, grid: &Array2<char>) -> Vec<Placement> {
+        let (num_rows, num_cols) = grid.dim();
+        let mut placements = Vec::new();
+        for (variant, template) in self.variants.iter().enumerate() {
+            let (template_rows, template_cols) = template.dim();
+            let (Some(max_row), Some(max_col)) = (
+                num_rows.checked_sub(template_rows),
+                num_cols.checked_sub(template_cols),
+            ) else {
+                continue;
+            };
+            for row in 0..=max_row {
+                for col in 0..=max_col {
+                    if template.matches_at(grid, row, col) {
+                        placements.push(Placement { row, col, variant });
+                    }
+                }
+            }
+        }
+        placements
+    }
+
+    pub fn count(&self, grid: &Array2<char>) -> usize {
+        self.find(grid).len()
+    }
+
+    pub fn cells(&self, placement: &Placement) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.variants[placement.variant].cells(placement.row, placement.col)
+    }
+}
@@ -0,0 +1,205 @@
+use std::fmt::{self, Display};
+
+use aho_corasick::AhoCorasick;
+use itertools::chain;
+use ndarray::Array2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl Direction {
+    // Change in (row, col) for each step in this direction
+    pub fn delta(&self) -> (isize, isize) {
+        match self {
+            Direction::North => (-1, 0),
+            Direction::NorthEast => (-1, 1),
+            Direction::East => (0, 1),
+            Direction::SouthEast => (1, 1),
+            Direction::South => (1, 0),
+            Direction::SouthWest => (1, -1),
+            Direction::West => (0, -1),
+            Direction::NorthWest => (-1, -1),
+        }
+    }
+
+    pub fn opposite(&self) -> Self {
+        match self {
+            Direction::North => Direction::South,
+            Direction::NorthEast => Direction::SouthWest,
+            Direction::East => Direction::West,
+            Direction::SouthEast => Direction::NorthWest,
+            Direction::South => Direction::North,
+            Direction::SouthWest => Direction::NorthEast,
+            Direction::West => Direction::East,
+            Direction::NorthWest => Direction::SouthEast,
+        }
+    }
+}
+
+impl Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Direction::North => "N",
+            Direction::NorthEast => "NE",
+            Direction::East => "E",
+            Direction::SouthEast => "SE",
+            Direction::South => "S",
+            Direction::SouthWest => "SW",
+            Direction::West => "W",
+            Direction::NorthWest => "NW",
+        };
+        write!(f, "{s}")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WordMatch {
+    // Index of the matched word, in the order the words were given
+    pub word: usize,
+    pub row: usize,
+    pub col: usize,
+    pub direction: Direction,
+}
+
+impl WordMatch {
+    pub fn cells(&self, len: usize) -> impl Iterator<Item = (usize, usize)> {
+        let (dr, dc) = self.direction.delta();
+        let (row, col) = (self.row as isize, self.col as isize);
+        (0..len as isize).map(move |i| ((row + i * dr) as usize, (col + i * dc) as usize))
+    }
+}
+
+struct Line {
+    direction: Direction,
+    cells: Vec<(usize, usize)>,
+}
+
+impl Line {
+    fn reversed(&self) -> Self {
+        Self {
+            direction: self.direction.opposite(),
+            cells: self.cells.iter().rev().copied().collect(),
+        }
+    }
+}
+
+// Every row, column and diagonal of the grid, read forwards
+fn lines(grid: &Array2<char>) -> impl Iterator<Item = Line> {
+    let (num_rows, num_cols) = grid.dim();
+    let num_diagonals = (num_rows + num_cols).saturating_sub(1);
+
+    chain!(
+        (0..num_rows).map(move |r| Line {
+            direction: Direction::East,
+            cells: (0..num_cols).map(|c| (r, c)).collect(),
+        }),
+        (0..num_cols).map(move |c| Line {
+            direction: Direction::South,
+            cells: (0..num_rows).map(|r| (r, c)).collect(),
+        }),
+        // Forward diagonals
+        (0..num_diagonals).map(move |start| Line {
+            direction: Direction::SouthEast,
+            cells: (0..num_rows)
+                .filter_map(|r| {
+                    let c = num_cols as i32 - 1 - start as i32 + r as i32;
+                    ((0..num_cols as i32).contains(&c)).then_some((r, c as usize))
+                })
+                .collect(),
+        }),
+        // Backward diagonals
+        (0..num_diagonals).map(move |start| Line {
+            direction: Direction::SouthWest,
+            cells: (0..num_rows)
+                .filter_map(|r| {
+                    let c = start as i32 - r as i32;
+                    ((0..num_cols as i32).contains(&c)).then_some((r, c as usize))
+                })
+                .collect(),
+        }),
+    )
+}
+
+// Finds words in all 8 directions of a grid. A palindrome is found once per
+// direction it reads in, so it counts twice along each line it lies on.
+pub struct WordSearch {
+    words: Vec<String>,
+    automaton: AhoCorasick,
+}
+
+impl WordSearch {
+    pub fn new<I, S>(words: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let words = words.into_iter().map(Into::into).collect::<Vec<String>>();
+        assert!(
+            words.iter().all(|word| !word.is_empty()),
+            "Cannot search for an empty word"
+        );
+        let automaton = AhoCorasick::new(&words).unwrap();
+        Self { words, automaton }
+    }
+
+    pub fn words(&self) -> &[String] {
+        &self.words
+    }
+
+    pub fn find(&self, grid: &Array2<char>) -> Vec<WordMatch> {
+        let mut matches = Vec::new();
+        for forwards in lines(grid) {
+            let backwards = forwards.reversed();
+            for line in [forwards, backwards] {
+                let haystack = line.cells.iter().map(|&(r, c)| grid[[r, c]]);
+                let offsets = haystack
+                    .clone()
+                    .scan(0, |offset, ch| {
+                        let start = *offset;
+                        *offset += ch.len_utf8();
+                        Some(start)
+                    })
+                    .collect::<Vec<_>>();
+                let haystack = haystack.collect::<String>();
+
+                for m in self.automaton.find_overlapping_iter(&haystack) {
+                    let (row, col) = line.cells[offsets.binary_search(&m.start()).unwrap()];
+                    matches.push(WordMatch {
+                        word: m.pattern().as_usize(),
+                        row,
+                        col,
+                        direction: line.direction,
+                    });
+                }
+            }
+        }
+        matches
+    }
+
+    // Number of occurrences of each word, in the order the words were given
+    pub fn counts(&self, grid: &Array2<char>) -> Vec<usize> {
+        let mut counts = vec![0; self.words.len()];
+        for m in self.find(grid) {
+            counts[m.word] += 1;
+        }
+        counts
+    }
+
+    pub fn count(&self, grid: &Array2<char>) -> usize {
+        self.find(grid).len()
+    }
+
+    // Cells covered by a match, for rendering
+    pub fn match_cells(&self, m: &WordMatch) -> impl Iterator<Item = (usize, usize)> {
+        m.cells(self.words[m.word].chars().count())
+    }
+}
@@ -0,0 +1,52 @@
+use std::{collections::HashSet, fmt::Write};
+
+use crate::{build_before_rules, find_violations};
+
+// Renders the ordering rules as a Graphviz digraph with an edge per rule. Pages
+// of the given update are filled in and labelled with their position, and
+// rules the update violates are drawn in red. With `induced`, only rules
+// between pages of the update are included.
+pub fn to_dot(rules: &[(u32, u32)], update: Option<&[u32]>, induced: bool) -> String {
+    let pages = update.unwrap_or_default();
+    let in_update = pages.iter().copied().collect::<HashSet<_>>();
+    let violated = find_violations(pages, &build_before_rules(rules))
+        .into_iter()
+        .map(|v| (v.before, v.after))
+        .collect::<HashSet<_>>();
+
+    let mut edges = rules
+        .iter()
+        .copied()
+        .filter(|(a, b)| !induced || (in_update.contains(a) && in_update.contains(b)))
+        .collect::<Vec<_>>();
+    edges.sort_unstable();
+    edges.dedup();
+    let mut nodes = edges
+        .iter()
+        .flat_map(|&(a, b)| [a, b])
+        .chain(pages.iter().copied())
+        .collect::<Vec<_>>();
+    nodes.sort_unstable();
+    nodes.dedup();
+
+    let mut result = String::from("digraph rules {\n    node [shape=circle];\n");
+    for page in nodes {
+        match pages.iter().position(|&p| p == page) {
+            Some(i) => writeln!(
+                result,
+                "    {page} [label=\"{page}\\n#{i}\", style=filled, fillcolor=lightblue];"
+            ),
+            None => writeln!(result, "    {page};"),
+        }
+        .unwrap();
+    }
+    for (a, b) in edges {
+        if violated.contains(&(a, b)) {
+            writeln!(result, "    {a} -> {b} [color=red, penwidth=2];").unwrap();
+        } else {
+            writeln!(result, "    {a} -> {b};").unwrap();
+        }
+    }
+    result.push_str("}\n");
+    result
+}
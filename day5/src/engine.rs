@@ -0,0 +1,217 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, BufRead, Write},
+    num::ParseIntError,
+};
+
+use crate::{find_violations, has_correct_ordering, reorder, CycleError, GetMiddle, Violation};
+
+// Ordering rules that can change while updates are checked against them. Along
+// with the rules themselves, the transitive closure is kept up to date so that
+// implied orderings can be queried directly.
+#[derive(Default)]
+pub struct RuleSet {
+    before_rules: HashMap<u32, HashSet<u32>>,
+    after_rules: HashMap<u32, HashSet<u32>>,
+    descendants: HashMap<u32, HashSet<u32>>,
+    ancestors: HashMap<u32, HashSet<u32>>,
+}
+
+impl RuleSet {
+    pub fn new(rules: &[(u32, u32)]) -> Self {
+        let mut result = Self::default();
+        for &(before, after) in rules {
+            result.add_rule(before, after);
+        }
+        result
+    }
+
+    pub fn before_rules(&self) -> &HashMap<u32, HashSet<u32>> {
+        &self.before_rules
+    }
+
+    pub fn has_rule(&self, before: u32, after: u32) -> bool {
+        self.before_rules
+            .get(&before)
+            .is_some_and(|set| set.contains(&after))
+    }
+
+    // Whether some chain of rules puts `before` ahead of `after`
+    pub fn implies(&self, before: u32, after: u32) -> bool {
+        self.descendants
+            .get(&before)
+            .is_some_and(|set| set.contains(&after))
+    }
+
+    // Returns false if the rule was already present
+    pub fn add_rule(&mut self, before: u32, after: u32) -> bool {
+        if !self.before_rules.entry(before).or_default().insert(after) {
+            return false;
+        }
+        self.after_rules.entry(after).or_default().insert(before);
+
+        // Everything at or above `before` now also precedes everything at or
+        // below `after`
+        let mut sources = self.ancestors.get(&before).cloned().unwrap_or_default();
+        sources.insert(before);
+        let mut targets = self.descendants.get(&after).cloned().unwrap_or_default();
+        targets.insert(after);
+        for &source in &sources {
+            self.descendants.entry(source).or_default().extend(&targets);
+        }
+        for &target in &targets {
+            self.ancestors.entry(target).or_default().extend(&sources);
+        }
+        true
+    }
+
+    // Returns false if the rule was not present
+    pub fn remove_rule(&mut self, before: u32, after: u32) -> bool {
+        if !self
+            .before_rules
+            .get_mut(&before)
+            .is_some_and(|set| set.remove(&after))
+        {
+            return false;
+        }
+        self.after_rules.get_mut(&after).unwrap().remove(&before);
+
+        // Only pages at or above `before` can lose descendants, and only pages
+        // at or below `after` can lose ancestors
+        let mut sources = self.ancestors.get(&before).cloned().unwrap_or_default();
+        sources.insert(before);
+        let mut targets = self.descendants.get(&after).cloned().unwrap_or_default();
+        targets.insert(after);
+        for source in sources {
+            let reachable = Self::reachable(source, &self.before_rules);
+            self.descendants.insert(source, reachable);
+        }
+        for target in targets {
+            let reachable = Self::reachable(target, &self.after_rules);
+            self.ancestors.insert(target, reachable);
+        }
+        true
+    }
+
+    fn reachable(start: u32, graph: &HashMap<u32, HashSet<u32>>) -> HashSet<u32> {
+        let mut result = HashSet::new();
+        let mut stack = vec![start];
+        while let Some(page) = stack.pop() {
+            for &next in graph.get(&page).into_iter().flatten() {
+                if result.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+        result
+    }
+
+    pub fn is_valid(&self, pages: &[u32]) -> bool {
+        has_correct_ordering(pages, &self.before_rules)
+    }
+
+    pub fn violations(&self, pages: &[u32]) -> Vec<Violation> {
+        find_violations(pages, &self.before_rules)
+    }
+
+    pub fn reorder(&self, pages: &[u32]) -> Result<Vec<u32>, CycleError> {
+        reorder(pages, &self.before_rules)
+    }
+
+    pub fn execute(&mut self, command: Command) -> String {
+        let join = |pages: &[u32]| {
+            pages
+                .iter()
+                .map(u32::to_string)
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+
+        match command {
+            Command::AddRule(before, after) if self.add_rule(before, after) => {
+                format!("added {before}|{after}")
+            }
+            Command::AddRule(before, after) => format!("{before}|{after} already present"),
+            Command::RemoveRule(before, after) if self.remove_rule(before, after) => {
+                format!("removed {before}|{after}")
+            }
+            Command::RemoveRule(before, after) => format!("{before}|{after} not present"),
+            Command::Query(before, after) if self.has_rule(before, after) => {
+                format!("{before} before {after}: by rule")
+            }
+            Command::Query(before, after) if self.implies(before, after) => {
+                format!("{before} before {after}: implied by other rules")
+            }
+            Command::Query(before, after) => format!("{before} before {after}: no"),
+            Command::Check(pages) if pages.is_empty() => String::from("empty update"),
+            Command::Check(pages) if self.is_valid(&pages) => {
+                format!("valid, middle {}", pages.as_slice().middle().unwrap())
+            }
+            Command::Check(pages) => match self.reorder(&pages) {
+                Ok(ordered) => format!(
+                    "invalid, reordered {}, middle {}",
+                    join(&ordered),
+                    ordered.as_slice().middle().unwrap()
+                ),
+                Err(e) => format!("invalid, {e}"),
+            },
+        }
+    }
+}
+
+pub enum Command {
+    AddRule(u32, u32),
+    RemoveRule(u32, u32),
+    Query(u32, u32),
+    Check(Vec<u32>),
+}
+
+impl Command {
+    // `a|b` adds a rule, `-a|b` removes it, `?a|b` asks whether the rules put
+    // a before b, and `a,b,c` checks an update
+    pub fn build(line: &str) -> Result<Self, ParseIntError> {
+        let parse_rule = |s: &str| -> Result<(u32, u32), ParseIntError> {
+            let (before, after) = s.split_once('|').unwrap_or((s, ""));
+            Ok((before.trim().parse()?, after.trim().parse()?))
+        };
+
+        let command = if let Some(rule) = line.strip_prefix('-') {
+            let (before, after) = parse_rule(rule)?;
+            Command::RemoveRule(before, after)
+        } else if let Some(rule) = line.strip_prefix('?') {
+            let (before, after) = parse_rule(rule)?;
+            Command::Query(before, after)
+        } else if line.contains('|') {
+            let (before, after) = parse_rule(line)?;
+            Command::AddRule(before, after)
+        } else {
+            let pages = line
+                .split_terminator(',')
+                .map(|page| page.trim().parse())
+                .collect::<Result<Vec<_>, _>>()?;
+            Command::Check(pages)
+        };
+        Ok(command)
+    }
+}
+
+// Reads commands line by line, writing a response to each
+pub fn interact<R: BufRead, W: Write>(
+    rule_set: &mut RuleSet,
+    input: R,
+    mut output: W,
+) -> io::Result<()> {
+    for line in input.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        match Command::build(line) {
+            Ok(command) => writeln!(output, "{}", rule_set.execute(command))?,
+            Err(e) => writeln!(output, "error: {e}")?,
+        }
+        output.flush()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,555 @@
+mod dot;
+mod engine;
+mod page_order;
+
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet},
+    error::Error,
+    fmt::{self, Display},
+    ops::ControlFlow,
+};
+
+use serde::Serialize;
+
+pub use dot::to_dot;
+pub use engine::{interact, Command, RuleSet};
+pub use page_order::{OrderError, PageOrder};
+
+pub trait GetMiddle<T> {
+    fn middle(&self) -> Option<&T>;
+}
+
+impl<T> GetMiddle<T> for &[T] {
+    fn middle(&self) -> Option<&T> {
+        self.get(self.len() / 2)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CycleError {
+    // Pages in rule order, where the last page must come before the first
+    pub pages: Vec<u32>,
+}
+
+impl CycleError {
+    // Rotates the cycle to start from its smallest page
+    pub fn new(mut pages: Vec<u32>) -> Self {
+        if let Some(smallest) = (0..pages.len()).min_by_key(|&i| pages[i]) {
+            pages.rotate_left(smallest);
+        }
+        Self { pages }
+    }
+}
+
+impl Display for CycleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Ordering rules form a cycle")?;
+        if let Some(first) = self.pages.first() {
+            write!(f, ": ")?;
+            for page in &self.pages {
+                write!(f, "{page} -> ")?;
+            }
+            write!(f, "{first}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for CycleError {}
+
+pub fn build_before_rules(rules: &[(u32, u32)]) -> HashMap<u32, HashSet<u32>> {
+    let mut result: HashMap<u32, HashSet<u32>> = HashMap::new();
+    for &(before, after) in rules {
+        result.entry(before).or_default().insert(after);
+    }
+    result
+}
+
+pub fn has_correct_ordering(pages: &[u32], before_rules: &HashMap<u32, HashSet<u32>>) -> bool {
+    let mut printed = HashSet::new();
+    pages
+        .iter()
+        .try_for_each(|page| {
+            if let Some(after) = before_rules.get(page) {
+                if printed.intersection(after).next().is_some() {
+                    return ControlFlow::Break(());
+                }
+            }
+            printed.insert(*page);
+            ControlFlow::Continue(())
+        })
+        .is_continue()
+}
+
+// Walks backwards through rules between the remaining pages, each of which has
+// at least one remaining page that must come before it, until a page repeats.
+fn find_cycle(remaining: &HashSet<u32>, before_rules: &HashMap<u32, HashSet<u32>>) -> CycleError {
+    let predecessor = |page| {
+        remaining
+            .iter()
+            .copied()
+            .filter(|other| {
+                before_rules
+                    .get(other)
+                    .is_some_and(|set| set.contains(&page))
+            })
+            .min()
+            .unwrap()
+    };
+
+    let mut path = Vec::new();
+    let mut seen = HashMap::new();
+    let mut page = *remaining.iter().min().unwrap();
+    while !seen.contains_key(&page) {
+        seen.insert(page, path.len());
+        path.push(page);
+        page = predecessor(page);
+    }
+
+    let mut cycle = path.split_off(seen[&page]);
+    cycle.reverse();
+    CycleError::new(cycle)
+}
+
+// Topologically sorts the pages with Kahn's algorithm, considering only rules
+// between pages in the update. Ties are broken by position in the update.
+pub fn reorder(
+    pages: &[u32],
+    before_rules: &HashMap<u32, HashSet<u32>>,
+) -> Result<Vec<u32>, CycleError> {
+    let positions = pages
+        .iter()
+        .enumerate()
+        .map(|(i, &page)| (page, i))
+        .collect::<HashMap<_, _>>();
+    let successors = |page| {
+        before_rules
+            .get(&page)
+            .into_iter()
+            .flatten()
+            .filter(|next| positions.contains_key(next))
+    };
+
+    let mut in_degree = vec![0; pages.len()];
+    for &page in pages {
+        for next in successors(page) {
+            in_degree[positions[next]] += 1;
+        }
+    }
+
+    let mut ready = (0..pages.len())
+        .filter(|&i| in_degree[i] == 0)
+        .map(Reverse)
+        .collect::<BinaryHeap<_>>();
+    let mut ordered = Vec::with_capacity(pages.len());
+    while let Some(Reverse(i)) = ready.pop() {
+        ordered.push(pages[i]);
+        for next in successors(pages[i]) {
+            let j = positions[next];
+            in_degree[j] -= 1;
+            if in_degree[j] == 0 {
+                ready.push(Reverse(j));
+            }
+        }
+    }
+
+    if ordered.len() < pages.len() {
+        let remaining = (0..pages.len())
+            .filter(|&i| in_degree[i] > 0)
+            .map(|i| pages[i])
+            .collect::<HashSet<_>>();
+        return Err(find_cycle(&remaining, before_rules));
+    }
+    Ok(ordered)
+}
+
+// A topological order is the only valid one exactly when it is a Hamiltonian
+// path, with a rule directly linking each page to the next.
+pub fn is_unique_order(ordered: &[u32], before_rules: &HashMap<u32, HashSet<u32>>) -> bool {
+    ordered.windows(2).all(|pair| {
+        before_rules
+            .get(&pair[0])
+            .is_some_and(|set| set.contains(&pair[1]))
+    })
+}
+
+// Indices of updates whose pages can be validly ordered in more than one way.
+// Updates whose rules form a cycle have no valid order and are not included.
+pub fn find_ambiguous(rules: &[(u32, u32)], updates: &[Vec<u32>]) -> Vec<usize> {
+    let before_rules = build_before_rules(rules);
+
+    updates
+        .iter()
+        .enumerate()
+        .filter(|(_, pages)| {
+            reorder(pages, &before_rules)
+                .is_ok_and(|ordered| !is_unique_order(&ordered, &before_rules))
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Violation {
+    pub before: u32,
+    pub after: u32,
+    pub before_position: usize,
+    pub after_position: usize,
+}
+
+// Every rule `before|after` where `after` is printed earlier than `before`
+pub fn find_violations(pages: &[u32], before_rules: &HashMap<u32, HashSet<u32>>) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    for (after_position, &after) in pages.iter().enumerate() {
+        for (before_position, &before) in pages.iter().enumerate().skip(after_position + 1) {
+            if before_rules
+                .get(&before)
+                .is_some_and(|set| set.contains(&after))
+            {
+                violations.push(Violation {
+                    before,
+                    after,
+                    before_position,
+                    after_position,
+                });
+            }
+        }
+    }
+    violations
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Rejection {
+    // Index of the update in the input
+    pub update: usize,
+    pub pages: Vec<u32>,
+    pub violations: Vec<Violation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub corrected: Option<Vec<u32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cycle: Option<Vec<u32>>,
+    // Whether other orders would satisfy the rules just as well as the corrected one
+    pub ambiguous: bool,
+}
+
+impl Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |pages: &[u32]| {
+            pages
+                .iter()
+                .map(u32::to_string)
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        writeln!(f, "Update {} ({}):", self.update + 1, join(&self.pages))?;
+        for v in &self.violations {
+            writeln!(
+                f,
+                "  {}|{} violated: {} at position {} is after {} at position {}",
+                v.before, v.after, v.before, v.before_position, v.after, v.after_position
+            )?;
+        }
+        match (&self.corrected, &self.cycle) {
+            (Some(corrected), _) if self.ambiguous => write!(
+                f,
+                "  corrected order: {} (ambiguous, not the only valid order)",
+                join(corrected)
+            ),
+            (Some(corrected), _) => write!(f, "  corrected order: {}", join(corrected)),
+            (None, Some(cycle)) => {
+                write!(f, "  cannot reorder, rules cycle through {}", join(cycle))
+            }
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+// Explains every update that part 2 has to reorder
+pub fn explain(rules: &[(u32, u32)], updates: &[Vec<u32>]) -> Vec<Rejection> {
+    let before_rules = build_before_rules(rules);
+
+    updates
+        .iter()
+        .enumerate()
+        .filter(|(_, pages)| !has_correct_ordering(pages, &before_rules))
+        .map(|(update, pages)| {
+            let (corrected, cycle) = match reorder(pages, &before_rules) {
+                Ok(ordered) => (Some(ordered), None),
+                Err(CycleError { pages }) => (None, Some(pages)),
+            };
+            let ambiguous = corrected
+                .as_ref()
+                .is_some_and(|ordered| !is_unique_order(ordered, &before_rules));
+            Rejection {
+                update,
+                pages: pages.clone(),
+                violations: find_violations(pages, &before_rules),
+                corrected,
+                cycle,
+                ambiguous,
+            }
+        })
+        .collect()
+}
+
+pub fn part1(rules: &[(u32, u32)], updates: &[Vec<u32>]) -> u32 {
+    let before_rules = build_before_rules(rules);
+
+    updates
+        .iter()
+        .filter_map(|pages| {
+            has_correct_ordering(pages, &before_rules)
+                .then_some(pages.as_slice().middle().unwrap().to_owned())
+        })
+        .sum::<u32>()
+}
+
+pub fn part2(rules: &[(u32, u32)], updates: &[Vec<u32>]) -> Result<u32, CycleError> {
+    let order = PageOrder::new(rules);
+
+    updates
+        .iter()
+        .filter(|pages| !has_correct_ordering(pages, order.before_rules()))
+        .map(|pages| {
+            // Sorting needs the rules to totally order the update; otherwise
+            // fall back to a topological sort, which picks one of the valid
+            // orders deterministically. `find_ambiguous` flags those updates.
+            let mut ordered = pages.clone();
+            if order.sort(&mut ordered).is_err() {
+                ordered = reorder(pages, order.before_rules())?;
+            }
+            Ok(ordered.as_slice().middle().unwrap().to_owned())
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE_RULES: [(u32, u32); 21] = [
+        (47, 53),
+        (97, 13),
+        (97, 61),
+        (97, 47),
+        (75, 29),
+        (61, 13),
+        (75, 53),
+        (29, 13),
+        (97, 29),
+        (53, 29),
+        (61, 53),
+        (97, 53),
+        (61, 29),
+        (47, 13),
+        (75, 47),
+        (97, 75),
+        (47, 61),
+        (75, 61),
+        (47, 29),
+        (75, 13),
+        (53, 13),
+    ];
+
+    fn example_updates() -> Vec<Vec<u32>> {
+        vec![
+            vec![75, 47, 61, 53, 29],
+            vec![97, 61, 53, 29, 13],
+            vec![75, 29, 13],
+            vec![75, 97, 47, 61, 53],
+            vec![61, 13, 29],
+            vec![97, 13, 75, 29, 47],
+        ]
+    }
+
+    #[test]
+    fn test_example() {
+        assert_eq!(part1(&EXAMPLE_RULES, &example_updates()), 143);
+        assert_eq!(part2(&EXAMPLE_RULES, &example_updates()), Ok(123));
+    }
+
+    #[test]
+    fn test_reorder() {
+        let before_rules = build_before_rules(&EXAMPLE_RULES);
+        assert_eq!(
+            reorder(&[97, 13, 75, 29, 47], &before_rules),
+            Ok(vec![97, 75, 47, 29, 13])
+        );
+    }
+
+    #[test]
+    fn test_explain() {
+        let rejections = explain(&EXAMPLE_RULES, &example_updates());
+        assert_eq!(
+            rejections.iter().map(|r| r.update).collect::<Vec<_>>(),
+            [3, 4, 5]
+        );
+        assert_eq!(
+            rejections[0].violations,
+            [Violation {
+                before: 97,
+                after: 75,
+                before_position: 1,
+                after_position: 0,
+            }]
+        );
+        assert_eq!(rejections[1].violations.len(), 1);
+        assert_eq!(rejections[2].violations.len(), 4);
+        assert_eq!(
+            rejections[0].to_string(),
+            "Update 4 (75,97,47,61,53):\n  \
+             97|75 violated: 97 at position 1 is after 75 at position 0\n  \
+             corrected order: 97,75,47,61,53"
+        );
+        assert_eq!(
+            serde_json::to_string(&rejections[1]).unwrap(),
+            "{\"update\":4,\"pages\":[61,13,29],\"violations\":[{\"before\":29,\"after\":13,\
+             \"before_position\":2,\"after_position\":1}],\"corrected\":[61,29,13],\
+             \"ambiguous\":false}"
+        );
+    }
+
+    #[test]
+    fn test_page_order() {
+        let order = PageOrder::new(&EXAMPLE_RULES);
+        let mut pages = vec![97, 13, 75, 29, 47];
+        pages.sort_by(|a, b| order.cmp(a, b));
+        assert_eq!(pages, [97, 75, 47, 29, 13]);
+        assert_eq!(order.check(&pages), Ok(()));
+
+        let order = PageOrder::new(&[(1, 2), (2, 3), (3, 1), (1, 4), (4, 5), (5, 4)]);
+        assert_eq!(order.check(&[1, 2]), Ok(()));
+        assert_eq!(order.check(&[1, 5]), Err(OrderError::Unrelated(1, 5)));
+        assert_eq!(
+            order.check(&[5, 4]),
+            Err(OrderError::Cycle(CycleError::new(vec![4, 5])))
+        );
+        assert_eq!(
+            order.check(&[3, 2, 1]),
+            Err(OrderError::Cycle(CycleError::new(vec![1, 2, 3])))
+        );
+        let mut pages = vec![3, 2, 1];
+        assert!(order.sort(&mut pages).is_err());
+        assert_eq!(pages, [3, 2, 1]);
+
+        // A page that must come before itself
+        let order = PageOrder::new(&[(1, 1), (1, 2)]);
+        assert_eq!(
+            order.check(&[1, 2]),
+            Err(OrderError::Cycle(CycleError::new(vec![1])))
+        );
+        assert_eq!(
+            part2(&[(1, 1), (2, 1)], &[vec![1, 2]]),
+            Err(CycleError::new(vec![1]))
+        );
+        assert_eq!(
+            CycleError::new(vec![1]).to_string(),
+            "Ordering rules form a cycle: 1 -> 1"
+        );
+        assert_eq!(
+            CycleError::new(Vec::new()).to_string(),
+            "Ordering rules form a cycle"
+        );
+    }
+
+    #[test]
+    fn test_ambiguous() {
+        assert!(find_ambiguous(&EXAMPLE_RULES, &example_updates()).is_empty());
+
+        // 1 and 2 both come before 3, but nothing orders 1 and 2
+        let rules = [(1, 3), (2, 3), (3, 4)];
+        let updates = [vec![3, 2, 1], vec![1, 3, 4], vec![4, 3], vec![2, 1, 3]];
+        assert_eq!(find_ambiguous(&rules, &updates), [0, 3]);
+        let rejections = explain(&rules, &updates);
+        assert_eq!(rejections[0].corrected, Some(vec![2, 1, 3]));
+        assert!(rejections[0].ambiguous);
+        assert!(!rejections[1].ambiguous);
+        // The middle page no longer depends on hash set iteration order
+        assert_eq!(part2(&rules, &updates), Ok(1 + 4));
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let rules = [(1, 2), (2, 3), (3, 4), (1, 3)];
+        assert_eq!(
+            to_dot(&rules, Some(&[3, 1, 2]), true),
+            "digraph rules {\n    node [shape=circle];\n    \
+             1 [label=\"1\\n#1\", style=filled, fillcolor=lightblue];\n    \
+             2 [label=\"2\\n#2\", style=filled, fillcolor=lightblue];\n    \
+             3 [label=\"3\\n#0\", style=filled, fillcolor=lightblue];\n    \
+             1 -> 2;\n    \
+             1 -> 3 [color=red, penwidth=2];\n    \
+             2 -> 3 [color=red, penwidth=2];\n}\n"
+        );
+        let full = to_dot(&rules, None, false);
+        assert!(full.contains("    4;\n"));
+        assert_eq!(full.matches("->").count(), 4);
+        assert!(!full.contains("red"));
+    }
+
+    #[test]
+    fn test_rule_set() {
+        let mut rule_set = RuleSet::new(&[(1, 2), (2, 3)]);
+        assert!(rule_set.implies(1, 3));
+        assert!(!rule_set.implies(3, 1));
+        // Updates are only checked against rules between their own pages
+        assert!(rule_set.is_valid(&[3, 1]));
+        assert!(!rule_set.is_valid(&[3, 2, 1]));
+        assert!(rule_set.add_rule(3, 4));
+        assert!(!rule_set.add_rule(3, 4));
+        assert!(rule_set.implies(1, 4));
+        assert!(rule_set.add_rule(1, 3));
+        // 1 still reaches 3 directly after the chain through 2 is broken
+        assert!(rule_set.remove_rule(2, 3));
+        assert!(!rule_set.remove_rule(2, 3));
+        assert!(rule_set.implies(1, 4));
+        assert!(!rule_set.implies(2, 4));
+        assert!(rule_set.remove_rule(1, 3));
+        assert!(!rule_set.implies(1, 3));
+        assert_eq!(rule_set.reorder(&[4, 3]), Ok(vec![3, 4]));
+    }
+
+    #[test]
+    fn test_interact() {
+        let input = "\
+            75,47,61\n\
+            47|75\n\
+            75|61\n\
+            75,47,61\n\
+            ?47|61\n\
+            -47|75\n\
+            75|47\n\
+            75,47,61\n\
+            1|x\n";
+        let mut output = Vec::new();
+        interact(&mut RuleSet::default(), input.as_bytes(), &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "\
+            valid, middle 47\n\
+            added 47|75\n\
+            added 75|61\n\
+            invalid, reordered 47,75,61, middle 75\n\
+            47 before 61: implied by other rules\n\
+            removed 47|75\n\
+            added 75|47\n\
+            valid, middle 47\n\
+            error: invalid digit found in string\n"
+        );
+    }
+
+    #[test]
+    fn test_reorder_cycle() {
+        let rules = [(1, 2), (2, 3), (3, 1), (4, 1), (3, 5)];
+        let before_rules = build_before_rules(&rules);
+        let error = reorder(&[5, 4, 3, 2, 1], &before_rules).unwrap_err();
+        assert_eq!(error.pages, [1, 2, 3]);
+        assert_eq!(
+            error.to_string(),
+            "Ordering rules form a cycle: 1 -> 2 -> 3 -> 1"
+        );
+        // The cycle does not matter if the update leaves out one of its pages
+        assert_eq!(reorder(&[3, 2, 5], &before_rules), Ok(vec![2, 3, 5]));
+    }
+}
@@ -1,19 +1,11 @@
 use std::{
-    collections::{HashMap, HashSet},
+    env, fs,
     fs::File,
-    io::{BufRead, BufReader},
-    ops::ControlFlow,
+    io::{self, BufRead, BufReader},
+    process,
 };
 
-trait GetMiddle<T> {
-    fn middle(&self) -> Option<&T>;
-}
-
-impl<T> GetMiddle<T> for &[T] {
-    fn middle(&self) -> Option<&T> {
-        self.get(self.len() / 2)
-    }
-}
+use day5::{explain, find_ambiguous, interact, part1, part2, to_dot, RuleSet};
 
 struct RulesLoader<'a> {
     lines: &'a mut Box<dyn Iterator<Item = String>>,
@@ -64,81 +56,18 @@ impl Iterator for UpdatesLoader<'_> {
     }
 }
 
-fn build_before_rules(rules: &[(u32, u32)]) -> HashMap<u32, HashSet<u32>> {
-    let mut result: HashMap<u32, HashSet<u32>> = HashMap::new();
-    for &(before, after) in rules {
-        result.entry(before).or_default().insert(after);
-    }
-    result
+fn dot_usage() -> ! {
+    eprintln!("Usage: --dot <file> [update]");
+    process::exit(1);
 }
 
-fn build_after_rules(rules: &[(u32, u32)]) -> HashMap<u32, HashSet<u32>> {
-    let mut result: HashMap<u32, HashSet<u32>> = HashMap::new();
-    for &(before, after) in rules {
-        result.entry(after).or_default().insert(before);
-    }
-    result
-}
-
-fn has_correct_ordering(pages: &[u32], before_rules: &HashMap<u32, HashSet<u32>>) -> bool {
-    let mut printed = HashSet::new();
-    pages
-        .iter()
-        .try_for_each(|page| {
-            if let Some(after) = before_rules.get(page) {
-                if printed.intersection(after).next().is_some() {
-                    return ControlFlow::Break(());
-                }
-            }
-            printed.insert(*page);
-            ControlFlow::Continue(())
-        })
-        .is_continue()
-}
-
-// Assumes the given graph is acyclic
-fn find_leaf(target: u32, graph: &HashMap<u32, HashSet<u32>>, candidates: &HashSet<u32>) -> u32 {
-    if let Some(set) = graph.get(&target) {
-        if let Some(&child) = set.intersection(candidates).next() {
-            return find_leaf(child, graph, candidates);
-        }
+fn main() {
+    if env::args().nth(1).as_deref() == Some("--interactive") {
+        let mut rule_set = RuleSet::default();
+        interact(&mut rule_set, io::stdin().lock(), io::stdout()).unwrap();
+        return;
     }
-    target
-}
 
-fn part1(rules: &[(u32, u32)], updates: &[Vec<u32>]) -> u32 {
-    let before_rules = build_before_rules(rules);
-
-    updates
-        .iter()
-        .filter_map(|pages| {
-            has_correct_ordering(pages, &before_rules)
-                .then_some(pages.as_slice().middle().unwrap().to_owned())
-        })
-        .sum::<u32>()
-}
-
-fn part2(rules: &[(u32, u32)], updates: &[Vec<u32>]) -> u32 {
-    let before_rules = build_before_rules(rules);
-    let after_rules = build_after_rules(rules);
-
-    updates
-        .iter()
-        .filter(|pages| !has_correct_ordering(pages, &before_rules))
-        .map(|pages| {
-            let mut remaining = pages.clone().into_iter().collect::<HashSet<u32>>();
-            let mut ordered = Vec::new();
-            while let Some(&target) = remaining.iter().next() {
-                let leaf = find_leaf(target, &after_rules, &remaining);
-                ordered.push(leaf);
-                remaining.remove(&leaf);
-            }
-            ordered.as_slice().middle().unwrap().to_owned()
-        })
-        .sum::<u32>()
-}
-
-fn main() {
     let file = File::open("day5/data/input.txt").unwrap();
     let reader = BufReader::new(file);
     let lines = reader.lines().map_while(Result::ok);
@@ -148,6 +77,45 @@ fn main() {
     let updates_loader = UpdatesLoader::new(&mut lines);
     let updates = updates_loader.collect::<Vec<_>>();
 
-    println!("Part 1: {}", part1(&rules, &updates));
-    println!("Part 2: {}", part2(&rules, &updates));
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    match args.first().map(String::as_str) {
+        Some("--explain") => {
+            for rejection in explain(&rules, &updates) {
+                println!("{rejection}");
+            }
+        }
+        Some("--dot") => {
+            // With an update number, draws only the rules between its pages
+            let Some(path) = args.get(1) else { dot_usage() };
+            // Update numbers start at 1
+            let update = args.get(2).map(|n| {
+                n.parse::<usize>()
+                    .ok()
+                    .and_then(|n| n.checked_sub(1))
+                    .and_then(|i| updates.get(i))
+                    .unwrap_or_else(|| dot_usage())
+                    .as_slice()
+            });
+            fs::write(path, to_dot(&rules, update, update.is_some())).unwrap();
+        }
+        Some("--json") => {
+            let rejections = explain(&rules, &updates);
+            println!("{}", serde_json::to_string_pretty(&rejections).unwrap());
+        }
+        _ => {
+            println!("Part 1: {}", part1(&rules, &updates));
+            match part2(&rules, &updates) {
+                Ok(sum) => println!("Part 2: {sum}"),
+                Err(e) => println!("Part 2: {e}"),
+            }
+            let ambiguous = find_ambiguous(&rules, &updates);
+            if !ambiguous.is_empty() {
+                let updates = ambiguous
+                    .iter()
+                    .map(|i| (i + 1).to_string())
+                    .collect::<Vec<_>>();
+                eprintln!("Warning: ambiguous updates {}", updates.join(","));
+            }
+        }
+    }
 }
@@ -0,0 +1,118 @@
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt::{self, Display},
+};
+
+use itertools::Itertools;
+
+use crate::{build_before_rules, CycleError};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OrderError {
+    // No rule relates the two pages, so the comparator treats them as equal
+    Unrelated(u32, u32),
+    Cycle(CycleError),
+}
+
+impl Display for OrderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrderError::Unrelated(a, b) => write!(f, "No ordering rule relates pages {a} and {b}"),
+            OrderError::Cycle(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl Error for OrderError {}
+
+// Compares pages by the ordering rules. This is only a consistent total order
+// over an update that `check` accepts.
+pub struct PageOrder {
+    before_rules: HashMap<u32, HashSet<u32>>,
+}
+
+impl PageOrder {
+    pub fn new(rules: &[(u32, u32)]) -> Self {
+        Self {
+            before_rules: build_before_rules(rules),
+        }
+    }
+
+    pub fn before_rules(&self) -> &HashMap<u32, HashSet<u32>> {
+        &self.before_rules
+    }
+
+    pub fn precedes(&self, a: u32, b: u32) -> bool {
+        self.before_rules
+            .get(&a)
+            .is_some_and(|set| set.contains(&b))
+    }
+
+    pub fn cmp(&self, a: &u32, b: &u32) -> Ordering {
+        if a != b && self.precedes(*a, *b) {
+            Ordering::Less
+        } else if a != b && self.precedes(*b, *a) {
+            Ordering::Greater
+        } else {
+            Ordering::Equal
+        }
+    }
+
+    // Checks that exactly one rule relates every pair of pages, and that the
+    // rules are transitive over the update.
+    pub fn check(&self, pages: &[u32]) -> Result<(), OrderError> {
+        if let Some(&a) = pages.iter().find(|&&a| self.precedes(a, a)) {
+            return Err(OrderError::Cycle(CycleError::new(vec![a])));
+        }
+        for (&a, &b) in pages.iter().tuple_combinations() {
+            match (self.precedes(a, b), self.precedes(b, a)) {
+                (true, true) => return Err(OrderError::Cycle(CycleError::new(vec![a, b]))),
+                (false, false) => return Err(OrderError::Unrelated(a, b)),
+                _ => {}
+            }
+        }
+
+        // Every pair is related, so the rules are transitive exactly when no
+        // two pages have the same number of pages after them. Otherwise some
+        // three pages form a cycle.
+        let num_after = pages
+            .iter()
+            .map(|&a| {
+                pages
+                    .iter()
+                    .filter(|&&b| b != a && self.precedes(a, b))
+                    .count()
+            })
+            .collect::<HashSet<_>>();
+        if num_after.len() == pages.len() {
+            return Ok(());
+        }
+        // A tournament whose out-degrees are not all distinct has a 3-cycle
+        // (Landau's theorem)
+        let (a, b, c) = pages
+            .iter()
+            .copied()
+            .tuple_combinations()
+            .find_map(|(a, b, c)| {
+                if self.precedes(a, b) && self.precedes(b, c) && self.precedes(c, a) {
+                    Some((a, b, c))
+                } else if self.precedes(a, c) && self.precedes(c, b) && self.precedes(b, a) {
+                    Some((a, c, b))
+                } else {
+                    None
+                }
+            })
+            .expect("non-transitive tournament has a 3-cycle");
+        Err(OrderError::Cycle(CycleError::new(vec![a, b, c])))
+    }
+
+    // Sorts the pages if the rules totally order them, otherwise leaves them
+    // untouched.
+    pub fn sort(&self, pages: &mut [u32]) -> Result<(), OrderError> {
+        self.check(pages)?;
+        pages.sort_by(|a, b| self.cmp(a, b));
+        Ok(())
+    }
+}
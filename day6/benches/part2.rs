@@ -5,6 +5,7 @@ use std::{
 
 use criterion::{criterion_group, criterion_main, Criterion};
 use ndarray::Array2;
+use rayon::ThreadPoolBuilder;
 
 use day6::part2;
 
@@ -18,6 +19,12 @@ fn criterion_benchmark(c: &mut Criterion) {
     let grid = Array2::<char>::from_shape_vec((num_rows, num_cols), vec).unwrap();
 
     c.bench_function("part 2", |b| b.iter(|| part2(&grid)));
+
+    // The same work confined to one thread, as a baseline for the parallel run
+    let pool = ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+    c.bench_function("part 2 (1 thread)", |b| {
+        b.iter(|| pool.install(|| part2(&grid)))
+    });
 }
 
 criterion_group!(benches, criterion_benchmark);
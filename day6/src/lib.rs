@@ -1,10 +1,30 @@
-use std::{collections::HashSet, ops::ControlFlow};
+mod patrols;
+mod rules;
+mod search;
+mod steps;
+mod tile;
+mod trail;
+mod visited;
+mod visualize;
+
+use std::ops::ControlFlow;
 
 use anyhow::{anyhow, bail};
 use ndarray::Array2;
+use rayon::prelude::*;
+
+use steps::{Overlay, Steps};
+use visited::VisitedLog;
+
+pub use patrols::{Collision, GuardReport, PatrolReport, Patrols};
+pub use rules::{Rules, Turn};
+pub use search::{min_obstacles_to_escape, min_obstacles_to_trap};
+pub use tile::Tile;
+pub use trail::{render_trail, Trail};
+pub use visualize::{export, loop_frames, patrol_frames, play, Cell, Frame, FrameFormat};
 
 #[derive(Default, Debug, Clone, Copy, Hash, Eq, PartialEq)]
-enum Direction {
+pub enum Direction {
     #[default]
     North,
     East,
@@ -13,27 +33,43 @@ enum Direction {
 }
 
 impl Direction {
-    fn build(c: char) -> anyhow::Result<Self> {
-        let d = match c {
-            '^' => Direction::North,
-            '>' => Direction::East,
-            'v' => Direction::South,
-            '<' => Direction::West,
-            _ => {
-                bail!("Character '{c}' is not a valid direction");
-            }
-        };
-        Ok(d)
+    fn parse(c: char) -> Option<Self> {
+        match c {
+            '^' => Some(Direction::North),
+            '>' => Some(Direction::East),
+            'v' => Some(Direction::South),
+            '<' => Some(Direction::West),
+            _ => None,
+        }
+    }
+
+    fn delta(self) -> (isize, isize) {
+        match self {
+            Direction::North => (-1, 0),
+            Direction::East => (0, 1),
+            Direction::South => (1, 0),
+            Direction::West => (0, -1),
+        }
+    }
+
+    fn symbol(self) -> char {
+        match self {
+            Direction::North => '^',
+            Direction::East => '>',
+            Direction::South => 'v',
+            Direction::West => '<',
+        }
     }
 }
 
-enum EndCondition {
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum EndCondition {
     Cycle,
     OffGrid,
 }
 
-#[derive(Default, Clone, Copy, Hash, Eq, PartialEq)]
-struct Position {
+#[derive(Default, Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub struct Position {
     pub row: usize,
     pub col: usize,
     pub dir: Direction,
@@ -45,7 +81,7 @@ impl Position {
     }
 }
 
-#[derive(Default, Clone, Copy)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
 struct Step {
     next_position: Position,
     has_obstacle: bool,
@@ -61,175 +97,100 @@ impl Step {
 }
 
 struct Map {
-    grid: Array2<char>,
-    step_north_grid: Array2<Step>,
-    step_east_grid: Array2<Step>,
-    step_south_grid: Array2<Step>,
-    step_west_grid: Array2<Step>,
+    tiles: Array2<Tile>,
+    trail: Trail,
+    steps: Steps,
     start_position: Position,
     current_position: Position,
-    steps_log: HashSet<Position>,
+    steps_log: VisitedLog,
     record_path: bool,
 }
 
 impl Map {
-    fn init_steps_north(grid: &Array2<char>, steps: &mut Array2<Step>, col: usize) {
-        let (num_rows, _) = grid.dim();
-        let mut next_position = Position::new(0, col, Direction::East);
-        let mut has_obstacle = false;
-        for row in 0..num_rows {
-            if grid[[row, col]] == '#' {
-                next_position = Position::new(row + 1, col, Direction::East);
-                has_obstacle = true;
-            }
-            steps[[row, col]] = Step::new(next_position, has_obstacle);
-        }
-    }
-
-    fn init_steps_east(grid: &Array2<char>, steps: &mut Array2<Step>, row: usize) {
-        let (_, num_cols) = grid.dim();
-        let mut next_position = Position::new(row, num_cols - 1, Direction::South);
-        let mut has_obstacle = false;
-        for col in (0..num_cols).rev() {
-            if grid[[row, col]] == '#' {
-                next_position = Position::new(row, col.saturating_sub(1), Direction::South);
-                has_obstacle = true;
-            }
-            steps[[row, col]] = Step::new(next_position, has_obstacle);
-        }
-    }
-
-    fn init_steps_south(grid: &Array2<char>, steps: &mut Array2<Step>, col: usize) {
-        let (num_rows, _) = grid.dim();
-        let mut next_position = Position::new(num_rows - 1, col, Direction::West);
-        let mut has_obstacle = false;
-        for row in (0..num_rows).rev() {
-            if grid[[row, col]] == '#' {
-                next_position = Position::new(row.saturating_sub(1), col, Direction::West);
-                has_obstacle = true;
-            }
-            steps[[row, col]] = Step::new(next_position, has_obstacle);
-        }
+    fn build(grid: Array2<char>) -> anyhow::Result<Self> {
+        Self::with_rules(grid, Rules::default())
     }
 
-    fn init_steps_west(grid: &Array2<char>, steps: &mut Array2<Step>, row: usize) {
-        let (_, num_cols) = grid.dim();
-        let mut next_position = Position::new(row, 0, Direction::North);
-        let mut has_obstacle = false;
-        for col in 0..num_cols {
-            if grid[[row, col]] == '#' {
-                next_position = Position::new(row, col + 1, Direction::North);
-                has_obstacle = true;
-            }
-            steps[[row, col]] = Step::new(next_position, has_obstacle);
-        }
-    }
-
-    fn build(mut grid: Array2<char>) -> anyhow::Result<Self> {
+    fn with_rules(grid: Array2<char>, rules: Rules) -> anyhow::Result<Self> {
+        rules.check()?;
         let (num_rows, num_cols) = grid.dim();
 
-        let start_position @ Position { row, col, .. } = (0..num_rows)
+        let start_position = (0..num_rows)
             .filter_map(|row| {
                 (0..num_cols)
                     .filter_map(|col| {
-                        Direction::build(grid[[row, col]])
-                            .ok()
-                            .map(|dir| Position::new(row, col, dir))
+                        Direction::parse(grid[[row, col]]).map(|dir| Position::new(row, col, dir))
                     })
                     .next()
             })
             .next()
             .ok_or(anyhow!("No direction found on grid"))?;
 
-        grid[[row, col]] = '.';
-
-        let mut step_north_grid = Array2::<Step>::from_shape_vec(
-            (num_rows, num_cols),
-            vec![Step::default(); num_rows * num_cols],
-        )
-        .unwrap();
-        let mut step_east_grid = step_north_grid.clone();
-        let mut step_south_grid = step_north_grid.clone();
-        let mut step_west_grid = step_north_grid.clone();
-        for row in 0..num_rows {
-            Self::init_steps_east(&grid, &mut step_east_grid, row);
-            Self::init_steps_west(&grid, &mut step_west_grid, row);
-        }
-        for col in 0..num_cols {
-            Self::init_steps_north(&grid, &mut step_north_grid, col);
-            Self::init_steps_south(&grid, &mut step_south_grid, col);
+        let mut tiles = Array2::from_elem(grid.dim(), Tile::Empty);
+        for ((row, col), &c) in grid.indexed_iter() {
+            if (row, col) == (start_position.row, start_position.col) {
+                continue;
+            }
+            if Direction::parse(c).is_some() {
+                bail!("Found a second guard at ({row}, {col}), which needs Patrols");
+            }
+            tiles[[row, col]] = match Tile::build(c)? {
+                tile @ (Tile::Empty | Tile::Obstacle) => tile,
+                tile => bail!("Tile {tile:?} at ({row}, {col}) needs Patrols"),
+            };
         }
 
+        let steps = Steps::build(&tiles, rules);
+        let steps_log = VisitedLog::new((num_rows, num_cols), steps.is_sparse());
+
         let result = Self {
-            grid,
-            step_north_grid,
-            step_east_grid,
-            step_south_grid,
-            step_west_grid,
+            tiles,
+            trail: Trail::new((num_rows, num_cols)),
+            steps,
             start_position,
             current_position: start_position,
-            steps_log: HashSet::new(),
+            steps_log,
             record_path: true,
         };
 
         Ok(result)
     }
 
-    fn get_range(&self, next: Position) -> Box<dyn Iterator<Item = (usize, usize)>> {
-        let Position { row, col, dir } = self.current_position;
-        let from_row = move |r| (r, col);
-        let from_col = move |c| (row, c);
-        match dir {
-            Direction::North => {
-                debug_assert_eq!(col, next.col);
-                Box::new((next.row..=row).rev().map(from_row))
-            }
-            Direction::East => {
-                debug_assert_eq!(row, next.row);
-                Box::new((col..=next.col).map(from_col))
-            }
-            Direction::South => {
-                debug_assert_eq!(col, next.col);
-                Box::new((row..=next.row).map(from_row))
-            }
-            Direction::West => {
-                debug_assert_eq!(row, next.row);
-                Box::new((next.col..=col).rev().map(from_col))
-            }
-        }
+    fn get_range(&self, next: Position) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.steps.cells(self.current_position, next)
     }
 
-    fn update(&mut self) -> ControlFlow<EndCondition, ()> {
-        let Position { row, col, dir } = self.current_position;
+    fn next_step(&self) -> Step {
+        self.steps.get(self.current_position)
+    }
 
+    fn update(&mut self) -> ControlFlow<EndCondition, ()> {
         let Step {
             next_position,
             has_obstacle,
-        } = match dir {
-            Direction::North => self.step_north_grid[[row, col]],
-            Direction::East => self.step_east_grid[[row, col]],
-            Direction::South => self.step_south_grid[[row, col]],
-            Direction::West => self.step_west_grid[[row, col]],
-        };
+        } = self.next_step();
 
         if self.record_path {
-            for (r, c) in self.get_range(next_position) {
-                self.grid[[r, c]] = '_';
+            let dir = self.current_position.dir;
+            for (r, c) in self.steps.cells(self.current_position, next_position) {
+                self.trail.mark(r, c, dir);
             }
         }
 
-        if self.steps_log.contains(&next_position) {
-            return ControlFlow::Break(EndCondition::Cycle);
+        // Leaving the grid ends the patrol even if the position at the edge,
+        // already turned, happens to be in the log
+        if !has_obstacle {
+            self.current_position = next_position;
+            return ControlFlow::Break(EndCondition::OffGrid);
         }
 
         self.steps_log.insert(self.current_position);
-        self.current_position = next_position;
-
-        if has_obstacle {
-            ControlFlow::Continue(())
-        } else {
-            ControlFlow::Break(EndCondition::OffGrid)
+        if self.steps_log.contains(next_position) {
+            return ControlFlow::Break(EndCondition::Cycle);
         }
+
+        self.current_position = next_position;
+        ControlFlow::Continue(())
     }
 
     fn reset(&mut self) {
@@ -237,64 +198,678 @@ impl Map {
         self.steps_log.clear();
     }
 
-    fn update_step_grids(&mut self, row: usize, col: usize) {
-        Self::init_steps_east(&self.grid, &mut self.step_east_grid, row);
-        Self::init_steps_west(&self.grid, &mut self.step_west_grid, row);
-        Self::init_steps_north(&self.grid, &mut self.step_north_grid, col);
-        Self::init_steps_south(&self.grid, &mut self.step_south_grid, col);
+    // Patrols from the start, returning how the patrol ended along with each
+    // position the guard set off from and the step it took
+    fn walk(&mut self) -> (EndCondition, Vec<(Position, Step)>) {
+        self.reset();
+        let mut steps = Vec::new();
+        loop {
+            steps.push((self.current_position, self.next_step()));
+            if let ControlFlow::Break(end_condition) = self.update() {
+                break (end_condition, steps);
+            }
+        }
     }
 
     fn add_obstacle(&mut self, row: usize, col: usize) {
-        debug_assert_ne!(self.grid[[row, col]], '#');
-        self.grid[[row, col]] = '#';
-        self.update_step_grids(row, col);
+        debug_assert_eq!(self.tiles[[row, col]], Tile::Empty);
+        self.tiles[[row, col]] = Tile::Obstacle;
+        self.steps.update(&self.tiles, row, col);
     }
 
     fn remove_obstacle(&mut self, row: usize, col: usize) {
-        debug_assert_eq!(self.grid[[row, col]], '#');
-        self.grid[[row, col]] = '.';
-        self.update_step_grids(row, col);
+        debug_assert_eq!(self.tiles[[row, col]], Tile::Obstacle);
+        self.tiles[[row, col]] = Tile::Empty;
+        self.steps.update(&self.tiles, row, col);
+    }
+}
+
+// Follows steps from `start` until the guard leaves the grid or comes back to
+// a position it already turned at. Returns how the patrol ended along with the
+// last position reached, which for a cycle is where the guard rejoins its path.
+fn patrol<F>(start: Position, next_step: F, steps_log: &mut VisitedLog) -> (EndCondition, Position)
+where
+    F: Fn(Position) -> Step,
+{
+    steps_log.clear();
+    steps_log.insert(start);
+    let mut position = start;
+    loop {
+        let Step {
+            next_position,
+            has_obstacle,
+        } = next_step(position);
+        if !has_obstacle {
+            return (EndCondition::OffGrid, next_position);
+        }
+        if !steps_log.insert(next_position) {
+            return (EndCondition::Cycle, next_position);
+        }
+        position = next_position;
+    }
+}
+
+// Number of moves to walk once around the loop through `entry`
+fn cycle_length<F>(steps: &Steps, entry: Position, next_step: F) -> usize
+where
+    F: Fn(Position) -> Step,
+{
+    let mut position = entry;
+    let mut length = 0;
+    loop {
+        let next = next_step(position).next_position;
+        length += steps.distance(position, next);
+        position = next;
+        if next == entry {
+            break length;
+        }
+    }
+}
+
+// Walks the guard's patrol one cell at a time, yielding the position on entering
+// each cell, starting with the guard's initial position. Turning in place does
+// not yield a new position.
+pub struct GuardSim {
+    map: Map,
+    segment: std::vec::IntoIter<Position>,
+    started: bool,
+    step_count: usize,
+    bump_count: usize,
+    end_condition: Option<EndCondition>,
+    cycle_entry: Option<Position>,
+}
+
+impl GuardSim {
+    pub fn build(grid: &Array2<char>) -> anyhow::Result<Self> {
+        Self::with_rules(grid, Rules::default())
+    }
+
+    pub fn with_rules(grid: &Array2<char>, rules: Rules) -> anyhow::Result<Self> {
+        let map = Map::with_rules(grid.clone(), rules)?;
+        Ok(Self {
+            map,
+            segment: Vec::new().into_iter(),
+            started: false,
+            step_count: 0,
+            bump_count: 0,
+            end_condition: None,
+            cycle_entry: None,
+        })
+    }
+
+    pub fn start_position(&self) -> Position {
+        self.map.start_position
+    }
+
+    // Number of moves from one cell to the next so far
+    pub fn step_count(&self) -> usize {
+        self.step_count
+    }
+
+    // Ticks taken so far, counting each move and each bump into an obstacle
+    pub fn tick_count(&self) -> usize {
+        self.step_count + self.bump_count
+    }
+
+    // How the patrol ended, once the last segment has been entered
+    pub fn end_condition(&self) -> Option<EndCondition> {
+        self.end_condition
+    }
+
+    // Position at which the guard rejoins its earlier path, if it loops
+    pub fn cycle_entry(&self) -> Option<Position> {
+        self.cycle_entry
+    }
+
+    // Directions the guard has taken through each cell, covering every
+    // segment entered so far
+    pub fn trail(&self) -> &Trail {
+        &self.map.trail
+    }
+
+    // Runs the rest of the patrol
+    pub fn run(&mut self) -> EndCondition {
+        self.by_ref().for_each(drop);
+        self.end_condition.unwrap()
+    }
+}
+
+impl Iterator for GuardSim {
+    type Item = Position;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(position) = self.segment.next() {
+                if self.started {
+                    self.step_count += 1;
+                }
+                self.started = true;
+                return Some(position);
+            }
+            if self.end_condition.is_some() {
+                return None;
+            }
+
+            // Each segment runs from the current cell, which was already
+            // yielded unless this is the first segment, up to the next turn
+            let dir = self.map.current_position.dir;
+            let Step { next_position, .. } = self.map.next_step();
+            self.segment = self
+                .map
+                .get_range(next_position)
+                .skip(self.started as usize)
+                .map(|(row, col)| Position::new(row, col, dir))
+                .collect::<Vec<_>>()
+                .into_iter();
+            match self.map.update() {
+                ControlFlow::Continue(()) if next_position.dir != dir => {
+                    self.bump_count += self.map.steps.rules().bumps;
+                }
+                ControlFlow::Continue(()) => {}
+                ControlFlow::Break(end_condition) => {
+                    self.end_condition = Some(end_condition);
+                    if end_condition == EndCondition::Cycle {
+                        self.cycle_entry = Some(next_position);
+                    }
+                }
+            }
+        }
     }
 }
 
 pub fn part1(grid: &Array2<char>) -> usize {
     let mut map = Map::build(grid.clone()).unwrap();
     while map.update().is_continue() {}
-    let Map { grid, .. } = map;
-    grid.into_iter().filter(|&c| c == '_').count()
+    map.trail.count()
 }
 
-pub fn part2(grid: &Array2<char>) -> usize {
+// A cell where a new obstacle traps the guard, with the position at which the
+// guard first rejoins its own path and the number of moves around the loop
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoopObstacle {
+    pub row: usize,
+    pub col: usize,
+    pub entry: Position,
+    pub cycle_length: usize,
+}
+
+pub fn loop_obstacles(grid: &Array2<char>) -> Vec<LoopObstacle> {
+    loop_obstacles_with_rules(grid, Rules::default())
+}
+
+// Each candidate is checked independently against the unmodified step grids,
+// so candidates are spread across threads
+pub fn loop_obstacles_with_rules(grid: &Array2<char>, rules: Rules) -> Vec<LoopObstacle> {
     let (num_rows, num_cols) = grid.dim();
-    let mut map = Map::build(grid.clone()).unwrap();
-    let (start_row, start_col) = (map.current_position.row, map.current_position.col);
+    let mut map = Map::with_rules(grid.clone(), rules).unwrap();
+    let start = map.start_position;
     while map.update().is_continue() {}
 
     let default_visited = (0..num_rows)
         .flat_map(|r| {
             (0..num_cols)
                 .filter_map(|c| {
-                    ((r, c) != (start_row, start_col) && map.grid[[r, c]] == '_').then_some((r, c))
+                    ((r, c) != (start.row, start.col) && map.trail.is_visited(r, c))
+                        .then_some((r, c))
                 })
                 .collect::<Vec<_>>()
         })
         .collect::<Vec<_>>();
 
-    map.reset();
-    map.record_path = false;
-
+    let steps = &map.steps;
     default_visited
-        .into_iter()
-        .filter(|&(r, c)| {
-            map.add_obstacle(r, c);
-            let end_condition = loop {
-                if let ControlFlow::Break(b) = map.update() {
-                    break b;
+        .into_par_iter()
+        .map_init(
+            || VisitedLog::new(grid.dim(), steps.is_sparse()),
+            |steps_log, (row, col)| {
+                let overlay = Overlay::new(steps, row, col);
+                let next_step = |position| overlay.get(position);
+                match patrol(start, next_step, steps_log) {
+                    (EndCondition::Cycle, entry) => Some(LoopObstacle {
+                        row,
+                        col,
+                        entry,
+                        cycle_length: cycle_length(steps, entry, next_step),
+                    }),
+                    (EndCondition::OffGrid, _) => None,
                 }
-            };
-            map.reset();
-            map.remove_obstacle(r, c);
-            matches!(end_condition, EndCondition::Cycle)
-        })
-        .count()
+            },
+        )
+        .flatten()
+        .collect()
+}
+
+pub fn part2(grid: &Array2<char>) -> usize {
+    loop_obstacles(grid).len()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn build_grid(s: &str) -> Array2<char> {
+        let rows = s.lines().collect::<Vec<_>>();
+        let vec = rows.concat().chars().collect::<Vec<_>>();
+        Array2::from_shape_vec((rows.len(), rows[0].len()), vec).unwrap()
+    }
+
+    fn example() -> Array2<char> {
+        build_grid(include_str!("../data/example.txt"))
+    }
+
+    // Tiles under a grid, with the guard standing on an empty tile
+    fn tiles(grid: &Array2<char>) -> Array2<Tile> {
+        grid.map(|&c| Tile::build(c).unwrap_or(Tile::Empty))
+    }
+
+    #[test]
+    fn test_example() {
+        assert_eq!(part1(&example()), 41);
+        assert_eq!(part2(&example()), 6);
+    }
+
+    #[test]
+    fn test_guard_sim() {
+        let mut sim = GuardSim::build(&example()).unwrap();
+        let path = sim.by_ref().collect::<Vec<_>>();
+        assert_eq!(sim.end_condition(), Some(EndCondition::OffGrid));
+        assert_eq!(sim.step_count(), path.len() - 1);
+        assert_eq!(path[0], Position::new(6, 4, Direction::North));
+        assert_eq!(path[1], Position::new(5, 4, Direction::North));
+        assert_eq!(path[6], Position::new(1, 5, Direction::East));
+        assert_eq!(*path.last().unwrap(), Position::new(9, 7, Direction::South));
+        for pair in path.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            assert_eq!(a.row.abs_diff(b.row) + a.col.abs_diff(b.col), 1);
+        }
+        let cells = path.iter().map(|p| (p.row, p.col)).collect::<HashSet<_>>();
+        assert_eq!(cells.len(), 41);
+    }
+
+    #[test]
+    fn test_guard_sim_cycle() {
+        let grid = build_grid(".#..\n...#\n#^..\n..#.");
+        let mut sim = GuardSim::build(&grid).unwrap();
+        assert_eq!(sim.run(), EndCondition::Cycle);
+        assert_eq!(sim.step_count(), 4);
+
+        // The guard turns back onto its starting position at the edge, then
+        // walks off the grid
+        let grid = build_grid("#.\n^#\n#.");
+        let mut sim = GuardSim::build(&grid).unwrap();
+        assert_eq!(sim.run(), EndCondition::OffGrid);
+        assert_eq!(sim.end_condition(), Some(EndCondition::OffGrid));
+        let report = Patrols::build(&grid, Rules::default()).unwrap().run();
+        assert_eq!(report.guards[0].end_condition, EndCondition::OffGrid);
+    }
+
+    #[test]
+    fn test_patrol_frames() {
+        let frames = patrol_frames(&example()).unwrap().collect::<Vec<_>>();
+        assert_eq!(frames.len(), GuardSim::build(&example()).unwrap().count());
+        assert_eq!(frames[0].to_text().lines().nth(6), Some(".#..^....."));
+        assert_eq!(frames[2].to_text().lines().nth(4), Some("....^..#.."));
+        assert_eq!(frames[2].to_text().lines().nth(5), Some("....X....."));
+
+        let last = frames.last().unwrap();
+        let visited = last.cells().iter().filter(|&&c| c == Cell::Visited).count();
+        assert_eq!(visited, 40);
+        assert_eq!(last.cells()[[9, 7]], Cell::Guard(Direction::South));
+
+        let ppm = last.to_ppm();
+        assert!(ppm.starts_with(b"P6\n40 40\n255\n"));
+        assert_eq!(ppm.len(), "P6\n40 40\n255\n".len() + 40 * 40 * 3);
+    }
+
+    #[test]
+    fn test_loop_frames() {
+        let frames = loop_frames(&example()).unwrap().collect::<Vec<_>>();
+        assert_eq!(frames.len(), 6);
+        // Obstacle next to the guard's starting position
+        assert_eq!(
+            frames[0].to_text(),
+            "....#.....\n\
+             ....*****#\n\
+             ....*...*.\n\
+             ..#.*...*.\n\
+             ..XX*XX#*.\n\
+             ..X.*.X.*.\n\
+             .#XO^****.\n\
+             .XXXXXXX#.\n\
+             #XXXXXXX..\n\
+             ......#X..\n"
+        );
+        assert_eq!(FrameFormat::build("ppm").unwrap(), FrameFormat::Ppm);
+        assert!(FrameFormat::build("gif").is_err());
+    }
+
+    #[test]
+    fn test_loop_obstacles() {
+        let obstacles = loop_obstacles(&example());
+        let cells = obstacles.iter().map(|o| (o.row, o.col)).collect::<Vec<_>>();
+        assert_eq!(cells, [(6, 3), (7, 6), (7, 7), (8, 1), (8, 3), (9, 7)]);
+        assert_eq!(
+            obstacles[0],
+            LoopObstacle {
+                row: 6,
+                col: 3,
+                entry: Position::new(6, 4, Direction::North),
+                cycle_length: 18,
+            }
+        );
+        // Walking the loop from its entry takes the reported number of moves
+        for obstacle in obstacles {
+            let mut grid = example();
+            grid[[6, 4]] = '.';
+            grid[[obstacle.row, obstacle.col]] = '#';
+            grid[[obstacle.entry.row, obstacle.entry.col]] = obstacle.entry.dir.symbol();
+            let mut sim = GuardSim::build(&grid).unwrap();
+            assert_eq!(sim.run(), EndCondition::Cycle);
+            assert_eq!(sim.cycle_entry(), Some(obstacle.entry));
+            assert_eq!(sim.step_count(), obstacle.cycle_length);
+        }
+    }
+
+    #[test]
+    fn test_overlay_matches_rebuilt_grids() {
+        let grid = example();
+        let (num_rows, num_cols) = grid.dim();
+        let cells = (0..num_rows).flat_map(|r| (0..num_cols).map(move |c| (r, c)));
+        for turn in [Turn::Left, Turn::Right] {
+            for wrap in [false, true] {
+                let rules = Rules {
+                    turn,
+                    wrap,
+                    ..Rules::default()
+                };
+                let dense = Steps::with_sparse(&tiles(&grid), rules, false);
+                let sparse = Steps::with_sparse(&tiles(&grid), rules, true);
+                for (row, col) in cells.clone().filter(|&(r, c)| grid[[r, c]] == '.') {
+                    let mut modified = grid.clone();
+                    modified[[row, col]] = '#';
+                    let rebuilt = Steps::with_sparse(&tiles(&modified), rules, false);
+                    for base in [&dense, &sparse] {
+                        let overlay = Overlay::new(base, row, col);
+                        for (r, c) in cells.clone().filter(|&(r, c)| modified[[r, c]] != '#') {
+                            for dir in [
+                                Direction::North,
+                                Direction::East,
+                                Direction::South,
+                                Direction::West,
+                            ] {
+                                let position = Position::new(r, c, dir);
+                                assert_eq!(overlay.get(position), rebuilt.get(position));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_rules() {
+        let left = Rules {
+            turn: Turn::Left,
+            ..Rules::default()
+        };
+        let mut sim = GuardSim::with_rules(&example(), left).unwrap();
+        let path = sim.by_ref().collect::<Vec<_>>();
+        assert_eq!(sim.end_condition(), Some(EndCondition::OffGrid));
+        assert_eq!(path[5], Position::new(1, 4, Direction::North));
+        assert_eq!(path[6], Position::new(1, 3, Direction::West));
+
+        // Without edges, the guard from the example ends up circling forever
+        let wrap = Rules {
+            wrap: true,
+            ..Rules::default()
+        };
+        let mut sim = GuardSim::with_rules(&example(), wrap).unwrap();
+        assert_eq!(sim.run(), EndCondition::Cycle);
+
+        // A guard on an empty row of a wrapped map laps it
+        let grid = build_grid("#...\n..>.\n...#");
+        let mut sim = GuardSim::with_rules(&grid, wrap).unwrap();
+        let path = sim.by_ref().map(|p| (p.row, p.col)).collect::<Vec<_>>();
+        assert_eq!(path, [(1, 2), (1, 3), (1, 0), (1, 1), (1, 2)]);
+        assert_eq!(sim.end_condition(), Some(EndCondition::Cycle));
+        assert_eq!(
+            sim.cycle_entry(),
+            Some(Position::new(1, 2, Direction::East))
+        );
+
+        let slow = Rules {
+            bumps: 3,
+            ..Rules::default()
+        };
+        let mut sim = GuardSim::with_rules(&example(), slow).unwrap();
+        sim.run();
+        let mut turns = 0;
+        let mut sim_default = GuardSim::build(&example()).unwrap();
+        let mut dir = sim_default.start_position().dir;
+        for position in sim_default.by_ref() {
+            turns += (position.dir != dir) as usize;
+            dir = position.dir;
+        }
+        assert_eq!(sim.step_count(), sim_default.step_count());
+        assert_eq!(sim.tick_count(), sim.step_count() + 3 * turns);
+        assert_eq!(sim_default.tick_count(), sim_default.step_count() + turns);
+
+        let stuck = Rules {
+            bumps: 0,
+            ..Rules::default()
+        };
+        assert!(GuardSim::with_rules(&example(), stuck).is_err());
+        assert!(Patrols::build(&example(), stuck).is_err());
+    }
+
+    #[test]
+    fn test_loop_obstacles_with_rules() {
+        let left = Rules {
+            turn: Turn::Left,
+            ..Rules::default()
+        };
+        // Mirroring the map left to right swaps which way the guard turns
+        let mirrored = example().slice(ndarray::s![.., ..;-1]).to_owned();
+        let mut expected = loop_obstacles(&mirrored)
+            .iter()
+            .map(|o| (o.row, 9 - o.col, o.cycle_length))
+            .collect::<Vec<_>>();
+        expected.sort();
+        let actual = loop_obstacles_with_rules(&example(), left)
+            .iter()
+            .map(|o| (o.row, o.col, o.cycle_length))
+            .collect::<Vec<_>>();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_trail() {
+        let mut sim = GuardSim::build(&example()).unwrap();
+        sim.run();
+        let trail = sim.trail();
+        assert_eq!(trail.count(), 41);
+        assert_eq!(
+            trail.directions(4, 4),
+            vec![Direction::North, Direction::East]
+        );
+        assert_eq!(trail.symbol(2, 4), Some('|'));
+        assert_eq!(trail.symbol(1, 5), Some('-'));
+        assert_eq!(trail.symbol(6, 4), Some('+'));
+        assert_eq!(trail.symbol(0, 0), None);
+
+        let expected = concat!(
+            "....#.....\n",
+            "....+---+#\n",
+            "....|...|.\n",
+            "..#.|...|.\n",
+            "..+-+-+#|.\n",
+            "..|.|.|.|.\n",
+            ".#+-^-+-+.\n",
+            ".+----++#.\n",
+            "#+----+|..\n",
+            "......#|..\n",
+        );
+        assert_eq!(render_trail(&example()).unwrap(), expected);
+
+        // A second guard or a tile only patrols understand is rejected
+        assert!(GuardSim::build(&build_grid("^.\n.^")).is_err());
+        assert!(GuardSim::build(&build_grid("^.\n.%")).is_err());
+    }
+
+    #[test]
+    fn test_visited_log() {
+        for sparse in [false, true] {
+            let mut log = VisitedLog::new((3, 4), sparse);
+            let position = Position::new(2, 3, Direction::West);
+            assert!(!log.contains(position));
+            assert!(log.insert(position));
+            assert!(!log.insert(position));
+            assert!(log.contains(position));
+            assert!(!log.contains(Position::new(2, 3, Direction::North)));
+            log.clear();
+            assert!(!log.contains(position));
+            assert!(log.insert(position));
+        }
+    }
+
+    #[test]
+    fn test_sparse_map() {
+        assert!(!Steps::build(&tiles(&example()), Rules::default()).is_sparse());
+
+        // The example in the corner of a much larger, mostly empty map
+        let mut grid = Array2::from_elem((200, 300), '.');
+        grid.slice_mut(ndarray::s![..10, ..10]).assign(&example());
+        assert!(Steps::build(&tiles(&grid), Rules::default()).is_sparse());
+        let report = Patrols::build(&grid, Rules::default()).unwrap().run();
+        assert_eq!(part1(&grid), report.combined_coverage());
+        assert_eq!(part2(&grid), loop_obstacles(&grid).len());
+    }
+
+    #[test]
+    fn test_patrols_single_guard() {
+        let report = Patrols::build(&example(), Rules::default()).unwrap().run();
+        assert_eq!(report.guards.len(), 1);
+        assert_eq!(report.guards[0].end_condition, EndCondition::OffGrid);
+        assert_eq!(report.guards[0].coverage(), 41);
+        assert_eq!(report.combined_coverage(), 41);
+        assert!(report.collisions.is_empty());
+
+        let mut grid = example();
+        grid[[7, 6]] = '#';
+        let report = Patrols::build(&grid, Rules::default()).unwrap().run();
+        assert_eq!(report.guards[0].end_condition, EndCondition::Cycle);
+    }
+
+    #[test]
+    fn test_patrols_collisions() {
+        // Two guards walking towards each other meet in the middle cell
+        let grid = build_grid("#....\n.>.<.\n.....");
+        let report = Patrols::build(&grid, Rules::default()).unwrap().run();
+        assert_eq!(
+            report.collisions[0],
+            Collision {
+                tick: 1,
+                row: 1,
+                col: 2,
+                guards: (0, 1),
+            }
+        );
+        // With an even gap between them, the guards swap cells instead
+        let grid = build_grid(".>..<.");
+        let report = Patrols::build(&grid, Rules::default()).unwrap().run();
+        assert_eq!(report.collisions.len(), 1);
+        assert_eq!(report.collisions[0].tick, 2);
+        assert_eq!((report.collisions[0].row, report.collisions[0].col), (0, 3));
+        assert_eq!(report.guards[0].coverage(), 5);
+        assert_eq!(report.guards[1].coverage(), 5);
+        assert_eq!(report.combined_coverage(), 6);
+    }
+
+    #[test]
+    fn test_patrols_tiles() {
+        // The one-way tile sends the guard south, off the bottom edge
+        let grid = build_grid("..#..\n.>.S.\n.....");
+        let report = Patrols::build(&grid, Rules::default()).unwrap().run();
+        let cells = &report.guards[0].cells;
+        assert_eq!(report.guards[0].end_condition, EndCondition::OffGrid);
+        assert_eq!(cells.len(), 4);
+        assert!(cells.contains(&(2, 3)));
+
+        // The fragile tile turns the first guard, then lets the second through
+        let grid = build_grid(".v..\n....\n.%..\n....\n.^..");
+        let report = Patrols::build(&grid, Rules::default()).unwrap().run();
+        assert!(report.guards[0].cells.contains(&(1, 0)));
+        assert!(report.guards[1].cells.contains(&(0, 1)));
+        // Guards chasing each other around the same loop settle without meeting
+        let report = Patrols::build(&build_grid(".#..\n..v#\n#^..\n..#."), Rules::default())
+            .unwrap()
+            .run();
+        assert!(report
+            .guards
+            .iter()
+            .all(|g| g.end_condition == EndCondition::Cycle));
+        assert!(report.collisions.is_empty());
+        assert!(Patrols::build(&build_grid("..?.\n.^.."), Rules::default()).is_err());
+        assert!(Patrols::build(&build_grid("...."), Rules::default()).is_err());
+    }
+
+    #[test]
+    fn test_min_obstacles_to_trap() {
+        let loops = loop_obstacles(&example())
+            .iter()
+            .map(|o| (o.row, o.col))
+            .collect::<Vec<_>>();
+        let added = min_obstacles_to_trap(&example(), 3).unwrap().unwrap();
+        assert_eq!(added.len(), 1);
+        assert!(loops.contains(&added[0]));
+
+        // On an empty map the guard needs an obstacle for each of four turns
+        let grid = build_grid(".....\n.....\n..^..\n.....\n.....");
+        assert_eq!(min_obstacles_to_trap(&grid, 3).unwrap(), None);
+        let added = min_obstacles_to_trap(&grid, 4).unwrap().unwrap();
+        assert_eq!(added.len(), 4);
+
+        // Turning back to its starting heading at the edge is not a loop, and
+        // the guard leaves without passing any cell that could trap it
+        let edge = build_grid("#.\n^#\n#.");
+        assert_eq!(min_obstacles_to_trap(&edge, 2).unwrap(), None);
+
+        let mut trapped = grid.clone();
+        for &(row, col) in &added {
+            trapped[[row, col]] = '#';
+        }
+        assert_eq!(
+            GuardSim::build(&trapped).unwrap().run(),
+            EndCondition::Cycle
+        );
+    }
+
+    #[test]
+    fn test_min_obstacles_to_escape() {
+        assert_eq!(
+            min_obstacles_to_escape(&example(), 2).unwrap(),
+            Some(vec![])
+        );
+
+        let mut grid = example();
+        grid[[6, 3]] = '#';
+        let removed = min_obstacles_to_escape(&grid, 2).unwrap().unwrap();
+        assert_eq!(removed.len(), 1);
+        let (row, col) = removed[0];
+        assert_eq!(grid[[row, col]], '#');
+        grid[[row, col]] = '.';
+        assert_eq!(GuardSim::build(&grid).unwrap().run(), EndCondition::OffGrid);
+
+        // A guard boxed in on all sides needs one wall taken away
+        let grid = build_grid("###\n#^#\n###");
+        assert_eq!(min_obstacles_to_escape(&grid, 0).unwrap(), None);
+        assert_eq!(min_obstacles_to_escape(&grid, 1).unwrap().unwrap().len(), 1);
+
+        // Already leaves after turning back to its starting heading
+        let grid = build_grid("#.\n^#\n#.");
+        assert_eq!(min_obstacles_to_escape(&grid, 2).unwrap(), Some(vec![]));
+    }
 }
@@ -1,11 +1,25 @@
 use std::{
+    env,
     fs::File,
-    io::{BufRead, BufReader},
+    io::{self, BufRead, BufReader},
+    path::Path,
+    time::Duration,
 };
 
 use ndarray::Array2;
 
-use day6::{part1, part2};
+use day6::{
+    export, loop_frames, loop_obstacles, min_obstacles_to_escape, min_obstacles_to_trap, part1,
+    part2, patrol_frames, play, render_trail, FrameFormat, LoopObstacle, Patrols, Rules,
+};
+
+// Frames per second when animating, unless given on the command line
+const DEFAULT_FPS: u64 = 30;
+
+fn frame_delay(arg: Option<String>) -> Duration {
+    let fps = arg.map_or(DEFAULT_FPS, |s| s.parse::<u64>().unwrap());
+    Duration::from_millis(1000 / fps.max(1))
+}
 
 fn main() {
     let file = File::open("day6/data/input.txt").unwrap();
@@ -16,6 +30,88 @@ fn main() {
     let vec = rows.concat().chars().collect::<Vec<_>>();
     let grid = Array2::<char>::from_shape_vec((num_rows, num_cols), vec).unwrap();
 
-    println!("Part 1: {}", part1(&grid));
-    println!("Part 2: {}", part2(&grid));
+    let mut args = env::args().skip(1);
+    match args.next().as_deref() {
+        Some("--loops") => {
+            for LoopObstacle {
+                row,
+                col,
+                entry,
+                cycle_length,
+            } in loop_obstacles(&grid)
+            {
+                println!(
+                    "Obstacle at ({row}, {col}): loop of {cycle_length} moves entered at ({}, {}) heading {:?}",
+                    entry.row, entry.col, entry.dir
+                );
+            }
+        }
+        Some("--patrols") => {
+            let report = Patrols::build(&grid, Rules::default()).unwrap().run();
+            for (i, guard) in report.guards.iter().enumerate() {
+                let start = guard.start;
+                println!(
+                    "Guard {i} from ({}, {}): {} cells, {:?}",
+                    start.row,
+                    start.col,
+                    guard.coverage(),
+                    guard.end_condition
+                );
+            }
+            println!("Combined coverage: {}", report.combined_coverage());
+            for collision in report.collisions {
+                println!(
+                    "Guards {} and {} collide at ({}, {}) on tick {}",
+                    collision.guards.0,
+                    collision.guards.1,
+                    collision.row,
+                    collision.col,
+                    collision.tick
+                );
+            }
+        }
+        Some("--trail") => print!("{}", render_trail(&grid).unwrap()),
+        // --trap [max obstacles]
+        Some("--trap") => {
+            let max = args.next().map_or(2, |s| s.parse().unwrap());
+            match min_obstacles_to_trap(&grid, max).unwrap() {
+                Some(cells) => println!("Trapped by adding obstacles at {cells:?}"),
+                None => println!("No way to trap the guard with up to {max} obstacles"),
+            }
+        }
+        // --escape [max obstacles]
+        Some("--escape") => {
+            let max = args.next().map_or(2, |s| s.parse().unwrap());
+            match min_obstacles_to_escape(&grid, max).unwrap() {
+                Some(cells) => println!("Escapes after removing obstacles at {cells:?}"),
+                None => println!("No way out by removing up to {max} obstacles"),
+            }
+        }
+        // --animate [fps]
+        Some("--animate") => {
+            let delay = frame_delay(args.next());
+            play(patrol_frames(&grid).unwrap(), delay, &mut io::stdout()).unwrap();
+        }
+        // --animate-loops [fps]
+        Some("--animate-loops") => {
+            let delay = frame_delay(args.next());
+            play(loop_frames(&grid).unwrap(), delay, &mut io::stdout()).unwrap();
+        }
+        // --export <dir> <text|ppm> [loops]
+        Some("--export") => {
+            let dir = args.next().expect("Missing output directory");
+            let format = FrameFormat::build(&args.next().unwrap_or_default()).unwrap();
+            let count = if args.next().as_deref() == Some("loops") {
+                export(loop_frames(&grid).unwrap(), Path::new(&dir), format)
+            } else {
+                export(patrol_frames(&grid).unwrap(), Path::new(&dir), format)
+            }
+            .unwrap();
+            println!("Wrote {count} frames to {dir}");
+        }
+        _ => {
+            println!("Part 1: {}", part1(&grid));
+            println!("Part 2: {}", part2(&grid));
+        }
+    }
 }
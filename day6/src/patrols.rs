@@ -0,0 +1,291 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::bail;
+use ndarray::Array2;
+
+use crate::{Direction, EndCondition, Position, Rules, Tile};
+
+// Extra ticks simulated once every guard has settled into a loop, to catch
+// collisions between guards whose loops are out of phase
+const MAX_SETTLED_TICKS: usize = 1_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Collision {
+    pub tick: usize,
+    pub row: usize,
+    pub col: usize,
+    pub guards: (usize, usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GuardReport {
+    pub start: Position,
+    pub end_condition: EndCondition,
+    pub cells: HashSet<(usize, usize)>,
+}
+
+impl GuardReport {
+    pub fn coverage(&self) -> usize {
+        self.cells.len()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatrolReport {
+    pub guards: Vec<GuardReport>,
+    pub collisions: Vec<Collision>,
+}
+
+impl PatrolReport {
+    // Number of cells visited by at least one guard
+    pub fn combined_coverage(&self) -> usize {
+        self.guards
+            .iter()
+            .flat_map(|guard| &guard.cells)
+            .collect::<HashSet<_>>()
+            .len()
+    }
+}
+
+// A guard stepping from one cell to the next during a tick
+struct Move {
+    guard: usize,
+    from: (usize, usize),
+    to: (usize, usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct GuardState {
+    position: Position,
+    bumps: usize,
+}
+
+struct Guard {
+    state: GuardState,
+    start: Position,
+    end_condition: Option<EndCondition>,
+    cells: HashSet<(usize, usize)>,
+    // Tick at which each state was seen since the map last changed
+    seen: HashMap<GuardState, usize>,
+    // Length of the loop the guard is repeating, if it has settled into one
+    period: Option<usize>,
+}
+
+// Several guards patrolling one map at the same time. Each tick, every guard
+// still on the map either moves one cell or bumps into what is ahead of it, in
+// the order the guards appear on the map.
+pub struct Patrols {
+    tiles: Array2<Tile>,
+    rules: Rules,
+    guards: Vec<Guard>,
+    tick: usize,
+    collisions: Vec<Collision>,
+}
+
+impl Patrols {
+    pub fn build(grid: &Array2<char>, rules: Rules) -> anyhow::Result<Self> {
+        rules.check()?;
+        let mut guards = Vec::new();
+        let mut tiles = Array2::from_elem(grid.dim(), Tile::Empty);
+        for ((row, col), &c) in grid.indexed_iter() {
+            if let Some(dir) = Direction::parse(c) {
+                let position = Position::new(row, col, dir);
+                let state = GuardState { position, bumps: 0 };
+                guards.push(Guard {
+                    state,
+                    start: position,
+                    end_condition: None,
+                    cells: HashSet::from([(row, col)]),
+                    seen: HashMap::from([(state, 0)]),
+                    period: None,
+                });
+            } else {
+                tiles[[row, col]] = Tile::build(c)?;
+            }
+        }
+        if guards.is_empty() {
+            bail!("No guards found on grid");
+        }
+
+        Ok(Self {
+            tiles,
+            rules,
+            guards,
+            tick: 0,
+            collisions: Vec::new(),
+        })
+    }
+
+    // Cell ahead of a position, if it is on the map
+    fn ahead(&self, position: Position) -> Option<(usize, usize)> {
+        let (num_rows, num_cols) = self.tiles.dim();
+        let (dr, dc) = position.dir.delta();
+        let row = position.row as isize + dr;
+        let col = position.col as isize + dc;
+        if self.rules.wrap {
+            let row = row.rem_euclid(num_rows as isize);
+            let col = col.rem_euclid(num_cols as isize);
+            Some((row as usize, col as usize))
+        } else {
+            let on_map =
+                (0..num_rows as isize).contains(&row) && (0..num_cols as isize).contains(&col);
+            on_map.then_some((row as usize, col as usize))
+        }
+    }
+
+    fn is_active(guard: &Guard) -> bool {
+        guard.end_condition.is_none()
+    }
+
+    // Advances every active guard by one tick
+    fn update(&mut self) {
+        self.tick += 1;
+        let mut map_changed = false;
+        let mut moves = Vec::new();
+
+        for i in 0..self.guards.len() {
+            if !Self::is_active(&self.guards[i]) {
+                continue;
+            }
+            let GuardState { position, bumps } = self.guards[i].state;
+            let Some((row, col)) = self.ahead(position) else {
+                self.guards[i].end_condition = Some(EndCondition::OffGrid);
+                continue;
+            };
+
+            let tile = self.tiles[[row, col]];
+            let state = if tile.blocks() {
+                if tile == Tile::Fragile {
+                    self.tiles[[row, col]] = Tile::Empty;
+                    map_changed = true;
+                }
+                if bumps + 1 >= self.rules.bumps {
+                    let dir = self.rules.turned(position.dir);
+                    let position = Position::new(position.row, position.col, dir);
+                    GuardState { position, bumps: 0 }
+                } else {
+                    GuardState {
+                        position,
+                        bumps: bumps + 1,
+                    }
+                }
+            } else {
+                let dir = match tile {
+                    Tile::OneWay(dir) => dir,
+                    _ => position.dir,
+                };
+                moves.push(Move {
+                    guard: i,
+                    from: (position.row, position.col),
+                    to: (row, col),
+                });
+                GuardState {
+                    position: Position::new(row, col, dir),
+                    bumps: 0,
+                }
+            };
+
+            let guard = &mut self.guards[i];
+            guard.state = state;
+            guard.cells.insert((state.position.row, state.position.col));
+        }
+
+        self.find_collisions(&moves);
+
+        if map_changed {
+            // Earlier states no longer predict what happens next
+            for guard in &mut self.guards {
+                guard.seen.clear();
+                guard.period = None;
+            }
+        }
+        for guard in self.guards.iter_mut().filter(|g| Self::is_active(g)) {
+            if let Some(&tick) = guard.seen.get(&guard.state) {
+                guard.period.get_or_insert(self.tick - tick);
+            } else {
+                guard.seen.insert(guard.state, self.tick);
+            }
+        }
+    }
+
+    // Guards sharing a cell at the end of the tick, or passing through each
+    // other during it
+    fn find_collisions(&mut self, moves: &[Move]) {
+        let active = (0..self.guards.len())
+            .filter(|&i| Self::is_active(&self.guards[i]))
+            .collect::<Vec<_>>();
+        for (a, &i) in active.iter().enumerate() {
+            for &j in &active[a + 1..] {
+                let (p, q) = (self.guards[i].state.position, self.guards[j].state.position);
+                if (p.row, p.col) == (q.row, q.col) {
+                    self.collisions.push(Collision {
+                        tick: self.tick,
+                        row: p.row,
+                        col: p.col,
+                        guards: (i, j),
+                    });
+                }
+            }
+        }
+        for (a, m) in moves.iter().enumerate() {
+            for n in &moves[a + 1..] {
+                if m.from == n.to && m.to == n.from {
+                    self.collisions.push(Collision {
+                        tick: self.tick,
+                        row: m.to.0,
+                        col: m.to.1,
+                        guards: (m.guard, n.guard),
+                    });
+                }
+            }
+        }
+    }
+
+    pub fn run(mut self) -> PatrolReport {
+        let is_settled = |guards: &[Guard]| {
+            guards
+                .iter()
+                .all(|g| !Self::is_active(g) || g.period.is_some())
+        };
+        while !is_settled(&self.guards) {
+            self.update();
+        }
+
+        // No tile can break any more, so every remaining collision happens
+        // within one full cycle of all the loops together
+        let settled_ticks = self
+            .guards
+            .iter()
+            .filter(|g| Self::is_active(g))
+            .filter_map(|g| g.period)
+            .fold(1, |lcm, period| lcm / gcd(lcm, period) * period)
+            .min(MAX_SETTLED_TICKS);
+        if self.guards.iter().filter(|g| Self::is_active(g)).count() > 1 {
+            for _ in 0..settled_ticks {
+                self.update();
+            }
+        }
+
+        let guards = self
+            .guards
+            .into_iter()
+            .map(|guard| GuardReport {
+                start: guard.start,
+                end_condition: guard.end_condition.unwrap_or(EndCondition::Cycle),
+                cells: guard.cells,
+            })
+            .collect();
+        PatrolReport {
+            guards,
+            collisions: self.collisions,
+        }
+    }
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
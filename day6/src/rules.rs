@@ -0,0 +1,52 @@
+use anyhow::bail;
+
+use crate::Direction;
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Turn {
+    Left,
+    #[default]
+    Right,
+}
+
+// How the guard moves. The puzzle's guard turns right on each obstacle, stops
+// at the first bump, and leaves once it walks off the edge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rules {
+    pub turn: Turn,
+    // Walking off one edge brings the guard back in at the opposite edge
+    pub wrap: bool,
+    // Times the guard bumps into an obstacle before it turns, each taking a tick.
+    // This only affects timing: the guard turns the same way after any number
+    // of bumps, so its path and how the patrol ends never depend on it.
+    pub bumps: usize,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            turn: Turn::Right,
+            wrap: false,
+            bumps: 1,
+        }
+    }
+}
+
+impl Rules {
+    // A guard that never bumps would have to turn before reaching an obstacle
+    pub(crate) fn check(&self) -> anyhow::Result<()> {
+        if self.bumps == 0 {
+            bail!("Guard must bump into an obstacle at least once before turning");
+        }
+        Ok(())
+    }
+
+    pub(crate) fn turned(&self, dir: Direction) -> Direction {
+        match (self.turn, dir) {
+            (Turn::Right, Direction::North) | (Turn::Left, Direction::South) => Direction::East,
+            (Turn::Right, Direction::East) | (Turn::Left, Direction::West) => Direction::South,
+            (Turn::Right, Direction::South) | (Turn::Left, Direction::North) => Direction::West,
+            (Turn::Right, Direction::West) | (Turn::Left, Direction::East) => Direction::North,
+        }
+    }
+}
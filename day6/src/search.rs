@@ -0,0 +1,115 @@
+use std::collections::HashSet;
+
+use ndarray::Array2;
+
+use crate::{EndCondition, Map, Position, Step};
+
+// Cells the guard walks through, in the order it first reaches them, leaving
+// out its starting cell
+fn path_cells(map: &mut Map) -> (EndCondition, Vec<(usize, usize)>) {
+    let (end_condition, steps) = map.walk();
+    let start = (map.start_position.row, map.start_position.col);
+    let mut seen = HashSet::from([start]);
+    let mut cells = Vec::new();
+    for (from, Step { next_position, .. }) in steps {
+        for cell in map.steps.cells(from, next_position) {
+            if seen.insert(cell) {
+                cells.push(cell);
+            }
+        }
+    }
+    (end_condition, cells)
+}
+
+// Obstacles the guard bumps into, in the order it first reaches them
+fn bumped_obstacles(map: &mut Map) -> (EndCondition, Vec<(usize, usize)>) {
+    let (end_condition, steps) = map.walk();
+    let mut obstacles = Vec::new();
+    for (from, step) in steps {
+        let Step {
+            next_position,
+            has_obstacle,
+        } = step;
+        // A lap of a wrapped line ends where it started, without an obstacle
+        if has_obstacle && next_position.dir != from.dir {
+            let stop = Position::new(next_position.row, next_position.col, from.dir);
+            let obstacle = map.steps.advance(stop, 1);
+            if !obstacles.contains(&obstacle) {
+                obstacles.push(obstacle);
+            }
+        }
+    }
+    (end_condition, obstacles)
+}
+
+// Depth-first search for up to `depth` more obstacles that trap the guard.
+// Only obstacles on the guard's current path can change where it goes.
+fn trap(map: &mut Map, depth: usize, added: &mut Vec<(usize, usize)>) -> bool {
+    let (end_condition, cells) = path_cells(map);
+    if end_condition == EndCondition::Cycle {
+        return true;
+    }
+    if depth == 0 {
+        return false;
+    }
+    for (row, col) in cells {
+        map.add_obstacle(row, col);
+        added.push((row, col));
+        let found = trap(map, depth - 1, added);
+        map.remove_obstacle(row, col);
+        if found {
+            return true;
+        }
+        added.pop();
+    }
+    false
+}
+
+// Depth-first search for up to `depth` obstacles whose removal lets the guard
+// leave. Only obstacles the guard bumps into can change where it goes.
+fn escape(map: &mut Map, depth: usize, removed: &mut Vec<(usize, usize)>) -> bool {
+    let (end_condition, obstacles) = bumped_obstacles(map);
+    if end_condition == EndCondition::OffGrid {
+        return true;
+    }
+    if depth == 0 {
+        return false;
+    }
+    for (row, col) in obstacles {
+        map.remove_obstacle(row, col);
+        removed.push((row, col));
+        let found = escape(map, depth - 1, removed);
+        map.add_obstacle(row, col);
+        if found {
+            return true;
+        }
+        removed.pop();
+    }
+    false
+}
+
+// Fewest obstacles to add so that the guard ends up in a loop, searching sets
+// of up to `max_obstacles`. An empty list means the guard already loops.
+pub fn min_obstacles_to_trap(
+    grid: &Array2<char>,
+    max_obstacles: usize,
+) -> anyhow::Result<Option<Vec<(usize, usize)>>> {
+    let mut map = Map::build(grid.clone())?;
+    map.record_path = false;
+    let mut added = Vec::new();
+    let found = (0..=max_obstacles).any(|depth| trap(&mut map, depth, &mut added));
+    Ok(found.then_some(added))
+}
+
+// Fewest obstacles to remove so that a looping guard leaves the map, searching
+// sets of up to `max_obstacles`. An empty list means the guard already leaves.
+pub fn min_obstacles_to_escape(
+    grid: &Array2<char>,
+    max_obstacles: usize,
+) -> anyhow::Result<Option<Vec<(usize, usize)>>> {
+    let mut map = Map::build(grid.clone())?;
+    map.record_path = false;
+    let mut removed = Vec::new();
+    let found = (0..=max_obstacles).any(|depth| escape(&mut map, depth, &mut removed));
+    Ok(found.then_some(removed))
+}
@@ -0,0 +1,390 @@
+use ndarray::Array2;
+
+use crate::{Direction, Position, Rules, Step, Tile};
+
+// Below this fraction of obstacle cells, steps are found by searching sorted
+// obstacle lists rather than stored for every cell
+const SPARSE_DENSITY: f64 = 0.01;
+
+// For every cell and heading, where the guard next turns or leaves the grid
+// under a given set of movement rules
+#[derive(Clone)]
+struct StepGrids {
+    north: Array2<Step>,
+    east: Array2<Step>,
+    south: Array2<Step>,
+    west: Array2<Step>,
+}
+
+impl StepGrids {
+    // Cells of a row, or a column when heading north or south, in the order the
+    // guard walks them heading `dir`
+    fn line(dim: (usize, usize), dir: Direction, index: usize) -> Vec<(usize, usize)> {
+        let (num_rows, num_cols) = dim;
+        match dir {
+            Direction::North => (0..num_rows).rev().map(|r| (r, index)).collect(),
+            Direction::East => (0..num_cols).map(|c| (index, c)).collect(),
+            Direction::South => (0..num_rows).map(|r| (r, index)).collect(),
+            Direction::West => (0..num_cols).rev().map(|c| (index, c)).collect(),
+        }
+    }
+
+    fn steps_mut(&mut self, dir: Direction) -> &mut Array2<Step> {
+        match dir {
+            Direction::North => &mut self.north,
+            Direction::East => &mut self.east,
+            Direction::South => &mut self.south,
+            Direction::West => &mut self.west,
+        }
+    }
+
+    fn init_line(
+        tiles: &Array2<Tile>,
+        rules: &Rules,
+        steps: &mut Array2<Step>,
+        line: &[(usize, usize)],
+        dir: Direction,
+    ) {
+        let n = line.len();
+        let turned = rules.turned(dir);
+        let stop_at = |i: usize| {
+            let (row, col) = line[i];
+            Step::new(Position::new(row, col, turned), true)
+        };
+        let before = |i: usize| {
+            if rules.wrap {
+                (i + n - 1) % n
+            } else {
+                i.saturating_sub(1)
+            }
+        };
+
+        let mut next = if rules.wrap {
+            match line
+                .iter()
+                .position(|&(r, c)| tiles[[r, c]] == Tile::Obstacle)
+            {
+                Some(first) => stop_at(before(first)),
+                None => {
+                    // Nothing to stop the guard, so it laps the line forever
+                    for &(row, col) in line {
+                        steps[[row, col]] = Step::new(Position::new(row, col, dir), true);
+                    }
+                    return;
+                }
+            }
+        } else {
+            let (row, col) = line[n - 1];
+            Step::new(Position::new(row, col, turned), false)
+        };
+        for i in (0..n).rev() {
+            let (row, col) = line[i];
+            if tiles[[row, col]] == Tile::Obstacle {
+                next = stop_at(before(i));
+            }
+            steps[[row, col]] = next;
+        }
+    }
+
+    fn build(tiles: &Array2<Tile>, rules: &Rules) -> Self {
+        let empty = Array2::<Step>::default(tiles.dim());
+        let mut result = Self {
+            north: empty.clone(),
+            east: empty.clone(),
+            south: empty.clone(),
+            west: empty,
+        };
+        let (num_rows, num_cols) = tiles.dim();
+        for dir in [Direction::East, Direction::West] {
+            for row in 0..num_rows {
+                result.update_line(tiles, rules, dir, row);
+            }
+        }
+        for dir in [Direction::North, Direction::South] {
+            for col in 0..num_cols {
+                result.update_line(tiles, rules, dir, col);
+            }
+        }
+        result
+    }
+
+    fn update_line(&mut self, tiles: &Array2<Tile>, rules: &Rules, dir: Direction, index: usize) {
+        let line = Self::line(tiles.dim(), dir, index);
+        Self::init_line(tiles, rules, self.steps_mut(dir), &line, dir);
+    }
+
+    // Recomputes the row and column through a cell whose contents changed
+    fn update(&mut self, tiles: &Array2<Tile>, rules: &Rules, row: usize, col: usize) {
+        self.update_line(tiles, rules, Direction::East, row);
+        self.update_line(tiles, rules, Direction::West, row);
+        self.update_line(tiles, rules, Direction::North, col);
+        self.update_line(tiles, rules, Direction::South, col);
+    }
+
+    fn get(&self, position: Position) -> Step {
+        let Position { row, col, dir } = position;
+        match dir {
+            Direction::North => self.north[[row, col]],
+            Direction::East => self.east[[row, col]],
+            Direction::South => self.south[[row, col]],
+            Direction::West => self.west[[row, col]],
+        }
+    }
+}
+
+// Obstacle positions sorted along each row and column, for finding the next
+// obstacle ahead of the guard by binary search
+#[derive(Clone)]
+struct ObstacleIndex {
+    // Columns of the obstacles in each row
+    rows: Vec<Vec<usize>>,
+    // Rows of the obstacles in each column
+    cols: Vec<Vec<usize>>,
+}
+
+impl ObstacleIndex {
+    fn build(tiles: &Array2<Tile>) -> Self {
+        let (num_rows, num_cols) = tiles.dim();
+        let mut rows = vec![Vec::new(); num_rows];
+        let mut cols = vec![Vec::new(); num_cols];
+        // Row-major order leaves every list sorted
+        for ((row, col), _) in tiles.indexed_iter().filter(|&(_, &t)| t == Tile::Obstacle) {
+            rows[row].push(col);
+            cols[col].push(row);
+        }
+        Self { rows, cols }
+    }
+
+    fn insert(&mut self, row: usize, col: usize) {
+        let cols = &mut self.rows[row];
+        if let Err(i) = cols.binary_search(&col) {
+            cols.insert(i, col);
+        }
+        let rows = &mut self.cols[col];
+        if let Err(i) = rows.binary_search(&row) {
+            rows.insert(i, row);
+        }
+    }
+
+    fn remove(&mut self, row: usize, col: usize) {
+        let cols = &mut self.rows[row];
+        if let Ok(i) = cols.binary_search(&col) {
+            cols.remove(i);
+        }
+        let rows = &mut self.cols[col];
+        if let Ok(i) = rows.binary_search(&row) {
+            rows.remove(i);
+        }
+    }
+
+    fn get(&self, position: Position, dim: (usize, usize), rules: &Rules) -> Step {
+        let Position { row, col, dir } = position;
+        let (num_rows, num_cols) = dim;
+        let turned = rules.turned(dir);
+        let stop = |row, col| Step::new(Position::new(row, col, turned), true);
+
+        // Nearest obstacle ahead without wrapping, then the first one met
+        // after wrapping around
+        let (ahead, wrapped, edge) = match dir {
+            Direction::North => {
+                let obstacles = &self.cols[col];
+                let i = obstacles.partition_point(|&r| r < row);
+                (
+                    i.checked_sub(1).map(|i| obstacles[i]),
+                    obstacles.last().copied(),
+                    0,
+                )
+            }
+            Direction::East => {
+                let obstacles = &self.rows[row];
+                let i = obstacles.partition_point(|&c| c <= col);
+                (
+                    obstacles.get(i).copied(),
+                    obstacles.first().copied(),
+                    num_cols - 1,
+                )
+            }
+            Direction::South => {
+                let obstacles = &self.cols[col];
+                let i = obstacles.partition_point(|&r| r <= row);
+                (
+                    obstacles.get(i).copied(),
+                    obstacles.first().copied(),
+                    num_rows - 1,
+                )
+            }
+            Direction::West => {
+                let obstacles = &self.rows[row];
+                let i = obstacles.partition_point(|&c| c < col);
+                (
+                    i.checked_sub(1).map(|i| obstacles[i]),
+                    obstacles.last().copied(),
+                    0,
+                )
+            }
+        };
+        let obstacle = if rules.wrap { ahead.or(wrapped) } else { ahead };
+
+        match (obstacle, dir) {
+            (Some(r), Direction::North) => stop((r + 1) % num_rows, col),
+            (Some(c), Direction::East) => stop(row, (c + num_cols - 1) % num_cols),
+            (Some(r), Direction::South) => stop((r + num_rows - 1) % num_rows, col),
+            (Some(c), Direction::West) => stop(row, (c + 1) % num_cols),
+            // Nothing to stop the guard, so it laps the line forever
+            (None, _) if rules.wrap => Step::new(position, true),
+            (None, Direction::North | Direction::South) => {
+                Step::new(Position::new(edge, col, turned), false)
+            }
+            (None, Direction::East | Direction::West) => {
+                Step::new(Position::new(row, edge, turned), false)
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Lookup {
+    Dense(Box<StepGrids>),
+    Sparse(ObstacleIndex),
+}
+
+// Where the guard next turns or leaves the grid from any position, either
+// precomputed for every cell or searched for on demand, depending on how
+// crowded the map is
+#[derive(Clone)]
+pub(crate) struct Steps {
+    dim: (usize, usize),
+    rules: Rules,
+    lookup: Lookup,
+}
+
+impl Steps {
+    pub(crate) fn build(tiles: &Array2<Tile>, rules: Rules) -> Self {
+        let num_obstacles = tiles.iter().filter(|&&t| t == Tile::Obstacle).count();
+        let density = num_obstacles as f64 / tiles.len().max(1) as f64;
+        Self::with_sparse(tiles, rules, density < SPARSE_DENSITY)
+    }
+
+    pub(crate) fn with_sparse(tiles: &Array2<Tile>, rules: Rules, sparse: bool) -> Self {
+        let lookup = if sparse {
+            Lookup::Sparse(ObstacleIndex::build(tiles))
+        } else {
+            Lookup::Dense(Box::new(StepGrids::build(tiles, &rules)))
+        };
+        Self {
+            dim: tiles.dim(),
+            rules,
+            lookup,
+        }
+    }
+
+    pub(crate) fn is_sparse(&self) -> bool {
+        matches!(self.lookup, Lookup::Sparse(_))
+    }
+
+    pub(crate) fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    // Brings the steps up to date after the obstacle at a cell of `tiles` was
+    // added or removed
+    pub(crate) fn update(&mut self, tiles: &Array2<Tile>, row: usize, col: usize) {
+        match &mut self.lookup {
+            Lookup::Dense(grids) => grids.update(tiles, &self.rules, row, col),
+            Lookup::Sparse(index) if tiles[[row, col]] == Tile::Obstacle => index.insert(row, col),
+            Lookup::Sparse(index) => index.remove(row, col),
+        }
+    }
+
+    pub(crate) fn get(&self, position: Position) -> Step {
+        match &self.lookup {
+            Lookup::Dense(grids) => grids.get(position),
+            Lookup::Sparse(index) => index.get(position, self.dim, &self.rules),
+        }
+    }
+
+    // Moves needed to reach the given cell heading in `from.dir`, if the cell
+    // lies ahead of `from` on the same line
+    pub(crate) fn offset(&self, from: Position, row: usize, col: usize) -> Option<usize> {
+        let (num_rows, num_cols) = self.dim;
+        let (same_line, diff, len) = match from.dir {
+            Direction::North => (col == from.col, from.row as isize - row as isize, num_rows),
+            Direction::East => (row == from.row, col as isize - from.col as isize, num_cols),
+            Direction::South => (col == from.col, row as isize - from.row as isize, num_rows),
+            Direction::West => (row == from.row, from.col as isize - col as isize, num_cols),
+        };
+        let offset = if self.rules.wrap {
+            diff.rem_euclid(len as isize)
+        } else {
+            diff
+        };
+        (same_line && offset > 0).then_some(offset as usize)
+    }
+
+    // Moves from `from` to `to`, where `to` is the next position after `from`.
+    // Arriving back at the same position means a full lap of a wrapped line.
+    pub(crate) fn distance(&self, from: Position, to: Position) -> usize {
+        let (num_rows, num_cols) = self.dim;
+        if from == to {
+            match from.dir {
+                Direction::North | Direction::South => num_rows,
+                Direction::East | Direction::West => num_cols,
+            }
+        } else {
+            self.offset(from, to.row, to.col).unwrap_or(0)
+        }
+    }
+
+    // Cell reached after `moves` moves from `from`
+    pub(crate) fn advance(&self, from: Position, moves: usize) -> (usize, usize) {
+        let (num_rows, num_cols) = self.dim;
+        let (dr, dc) = from.dir.delta();
+        let row = (from.row as isize + dr * moves as isize).rem_euclid(num_rows as isize);
+        let col = (from.col as isize + dc * moves as isize).rem_euclid(num_cols as isize);
+        (row as usize, col as usize)
+    }
+
+    // Cells walked from `from` to `to` inclusive, where `to` is the next
+    // position after `from`
+    pub(crate) fn cells(
+        &self,
+        from: Position,
+        to: Position,
+    ) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..=self.distance(from, to)).map(move |moves| self.advance(from, moves))
+    }
+}
+
+// One extra obstacle layered over shared steps. Only steps along the
+// obstacle's row and column can change, and those are patched on lookup, so
+// the steps themselves are never copied.
+#[derive(Clone, Copy)]
+pub(crate) struct Overlay<'a> {
+    steps: &'a Steps,
+    row: usize,
+    col: usize,
+}
+
+impl<'a> Overlay<'a> {
+    pub(crate) fn new(steps: &'a Steps, row: usize, col: usize) -> Self {
+        Self { steps, row, col }
+    }
+
+    pub(crate) fn get(&self, position: Position) -> Step {
+        let step = self.steps.get(position);
+        if position.row != self.row && position.col != self.col {
+            return step;
+        }
+        // The extra obstacle applies if it lies ahead of the guard and no
+        // further than wherever the guard would otherwise stop
+        let reach = self.steps.distance(position, step.next_position);
+        match self.steps.offset(position, self.row, self.col) {
+            Some(offset) if offset <= reach => {
+                let (row, col) = self.steps.advance(position, offset - 1);
+                let dir = self.steps.rules.turned(position.dir);
+                Step::new(Position::new(row, col, dir), true)
+            }
+            _ => step,
+        }
+    }
+}
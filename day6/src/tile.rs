@@ -0,0 +1,33 @@
+use anyhow::bail;
+
+use crate::Direction;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tile {
+    Empty,
+    Obstacle,
+    // Turns any guard that steps onto it to face the given direction
+    OneWay(Direction),
+    // Stops a guard like an obstacle, but breaks on the first bump
+    Fragile,
+}
+
+impl Tile {
+    pub fn build(c: char) -> anyhow::Result<Self> {
+        let tile = match c {
+            '.' => Tile::Empty,
+            '#' => Tile::Obstacle,
+            'N' => Tile::OneWay(Direction::North),
+            'E' => Tile::OneWay(Direction::East),
+            'S' => Tile::OneWay(Direction::South),
+            'W' => Tile::OneWay(Direction::West),
+            '%' => Tile::Fragile,
+            _ => bail!("Character '{c}' is not a valid tile"),
+        };
+        Ok(tile)
+    }
+
+    pub(crate) fn blocks(self) -> bool {
+        matches!(self, Tile::Obstacle | Tile::Fragile)
+    }
+}
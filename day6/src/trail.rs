@@ -0,0 +1,87 @@
+use ndarray::Array2;
+
+use crate::{Direction, GuardSim, Tile};
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::North,
+    Direction::East,
+    Direction::South,
+    Direction::West,
+];
+
+// Headings the guard has passed through each cell with, one bit per direction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trail {
+    headings: Array2<u8>,
+}
+
+impl Trail {
+    pub(crate) fn new(dim: (usize, usize)) -> Self {
+        Self {
+            headings: Array2::zeros(dim),
+        }
+    }
+
+    pub(crate) fn mark(&mut self, row: usize, col: usize, dir: Direction) {
+        self.headings[[row, col]] |= 1 << dir as u8;
+    }
+
+    pub fn directions(&self, row: usize, col: usize) -> Vec<Direction> {
+        let headings = self.headings[[row, col]];
+        DIRECTIONS
+            .into_iter()
+            .filter(|&dir| headings & (1 << dir as u8) != 0)
+            .collect()
+    }
+
+    pub fn is_visited(&self, row: usize, col: usize) -> bool {
+        self.headings[[row, col]] != 0
+    }
+
+    // Number of cells visited
+    pub fn count(&self) -> usize {
+        self.headings.iter().filter(|&&h| h != 0).count()
+    }
+
+    // How the puzzle draws the path through a cell: `|` for up and down, `-`
+    // for left and right, and `+` where the two cross
+    pub fn symbol(&self, row: usize, col: usize) -> Option<char> {
+        let dirs = self.directions(row, col);
+        let vertical = dirs
+            .iter()
+            .any(|d| matches!(d, Direction::North | Direction::South));
+        let horizontal = dirs
+            .iter()
+            .any(|d| matches!(d, Direction::East | Direction::West));
+        match (vertical, horizontal) {
+            (true, true) => Some('+'),
+            (true, false) => Some('|'),
+            (false, true) => Some('-'),
+            (false, false) => None,
+        }
+    }
+}
+
+// The guard's whole patrol drawn as in the puzzle, with the guard at its start
+pub fn render_trail(grid: &Array2<char>) -> anyhow::Result<String> {
+    let mut sim = GuardSim::build(grid)?;
+    sim.run();
+    let start = sim.start_position();
+    let trail = sim.trail();
+
+    let mut result = String::new();
+    for (row, line) in sim.map.tiles.rows().into_iter().enumerate() {
+        for (col, &tile) in line.iter().enumerate() {
+            let symbol = if (row, col) == (start.row, start.col) {
+                start.dir.symbol()
+            } else if tile == Tile::Obstacle {
+                '#'
+            } else {
+                trail.symbol(row, col).unwrap_or('.')
+            };
+            result.push(symbol);
+        }
+        result.push('\n');
+    }
+    Ok(result)
+}
@@ -0,0 +1,79 @@
+use std::collections::HashSet;
+
+use crate::Position;
+
+// Records positions seen during a patrol. On dense maps there is one slot per
+// cell and heading, holding the generation in which it was last marked, so
+// clearing the log only needs a new generation rather than touching every
+// slot. Sparse maps have few places to turn, so a set of positions is smaller.
+pub(crate) enum VisitedLog {
+    Dense {
+        stamps: Vec<u32>,
+        generation: u32,
+        num_cols: usize,
+    },
+    Sparse(HashSet<Position>),
+}
+
+impl VisitedLog {
+    pub(crate) fn new((num_rows, num_cols): (usize, usize), sparse: bool) -> Self {
+        if sparse {
+            VisitedLog::Sparse(HashSet::new())
+        } else {
+            VisitedLog::Dense {
+                stamps: vec![0; num_rows * num_cols * 4],
+                generation: 1,
+                num_cols,
+            }
+        }
+    }
+
+    fn index(num_cols: usize, position: Position) -> usize {
+        let Position { row, col, dir } = position;
+        (row * num_cols + col) * 4 + dir as usize
+    }
+
+    pub(crate) fn clear(&mut self) {
+        match self {
+            VisitedLog::Dense {
+                stamps, generation, ..
+            } => {
+                *generation = generation.wrapping_add(1);
+                // Stamps from a previous lap of the counter would look current
+                if *generation == 0 {
+                    stamps.fill(0);
+                    *generation = 1;
+                }
+            }
+            VisitedLog::Sparse(positions) => positions.clear(),
+        }
+    }
+
+    pub(crate) fn contains(&self, position: Position) -> bool {
+        match self {
+            VisitedLog::Dense {
+                stamps,
+                generation,
+                num_cols,
+            } => stamps[Self::index(*num_cols, position)] == *generation,
+            VisitedLog::Sparse(positions) => positions.contains(&position),
+        }
+    }
+
+    // Returns whether the position was not already in the log
+    pub(crate) fn insert(&mut self, position: Position) -> bool {
+        match self {
+            VisitedLog::Dense {
+                stamps,
+                generation,
+                num_cols,
+            } => {
+                let i = Self::index(*num_cols, position);
+                let is_new = stamps[i] != *generation;
+                stamps[i] = *generation;
+                is_new
+            }
+            VisitedLog::Sparse(positions) => positions.insert(position),
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+    thread,
+    time::Duration,
+};
+
+use anyhow::bail;
+use ndarray::Array2;
+
+use crate::{
+    loop_obstacles,
+    steps::{Overlay, Steps},
+    Direction, GuardSim, LoopObstacle, Position, Tile,
+};
+
+const RESET: &str = "\x1b[0m";
+const CLEAR: &str = "\x1b[H\x1b[2J";
+
+// Side length in pixels of one grid cell in PPM frames
+const PPM_SCALE: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cell {
+    Empty,
+    Obstacle,
+    Visited,
+    Guard(Direction),
+    LoopObstacle,
+    Cycle,
+}
+
+impl Cell {
+    fn symbol(self) -> char {
+        match self {
+            Cell::Empty => '.',
+            Cell::Obstacle => '#',
+            Cell::Visited => 'X',
+            Cell::Guard(dir) => dir.symbol(),
+            Cell::LoopObstacle => 'O',
+            Cell::Cycle => '*',
+        }
+    }
+
+    fn ansi(self) -> &'static str {
+        match self {
+            Cell::Empty => "\x1b[2m",
+            Cell::Obstacle => "\x1b[1m",
+            Cell::Visited => "\x1b[34m",
+            Cell::Guard(_) => "\x1b[1;33m",
+            Cell::LoopObstacle => "\x1b[1;31m",
+            Cell::Cycle => "\x1b[35m",
+        }
+    }
+
+    fn rgb(self) -> [u8; 3] {
+        match self {
+            Cell::Empty => [20, 20, 20],
+            Cell::Obstacle => [160, 160, 160],
+            Cell::Visited => [40, 80, 200],
+            Cell::Guard(_) => [240, 200, 0],
+            Cell::LoopObstacle => [220, 30, 30],
+            Cell::Cycle => [200, 40, 200],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameFormat {
+    Text,
+    Ppm,
+}
+
+impl FrameFormat {
+    pub fn build(s: &str) -> anyhow::Result<Self> {
+        let format = match s {
+            "text" | "txt" => FrameFormat::Text,
+            "ppm" => FrameFormat::Ppm,
+            _ => bail!("Unknown frame format '{s}'"),
+        };
+        Ok(format)
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            FrameFormat::Text => "txt",
+            FrameFormat::Ppm => "ppm",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    cells: Array2<Cell>,
+}
+
+impl Frame {
+    fn new(tiles: &Array2<Tile>) -> Self {
+        let cells = tiles.map(|&tile| {
+            if tile == Tile::Obstacle {
+                Cell::Obstacle
+            } else {
+                Cell::Empty
+            }
+        });
+        Self { cells }
+    }
+
+    pub fn cells(&self) -> &Array2<Cell> {
+        &self.cells
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut result = String::new();
+        for row in self.cells.rows() {
+            result.extend(row.iter().map(|cell| cell.symbol()));
+            result.push('\n');
+        }
+        result
+    }
+
+    pub fn to_ansi(&self) -> String {
+        let mut result = String::new();
+        for row in self.cells.rows() {
+            for &cell in row {
+                result.push_str(cell.ansi());
+                result.push(cell.symbol());
+                result.push_str(RESET);
+            }
+            result.push('\n');
+        }
+        result
+    }
+
+    // Binary PPM (P6) image with each cell drawn as a square of pixels
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let (num_rows, num_cols) = self.cells.dim();
+        let (height, width) = (num_rows * PPM_SCALE, num_cols * PPM_SCALE);
+        let mut result = format!("P6\n{width} {height}\n255\n").into_bytes();
+        for row in self.cells.rows() {
+            let line = row
+                .iter()
+                .flat_map(|cell| cell.rgb().repeat(PPM_SCALE))
+                .collect::<Vec<_>>();
+            for _ in 0..PPM_SCALE {
+                result.extend_from_slice(&line);
+            }
+        }
+        result
+    }
+}
+
+// One frame per cell the guard enters, with the cells behind it marked visited
+pub fn patrol_frames(grid: &Array2<char>) -> anyhow::Result<impl Iterator<Item = Frame>> {
+    let sim = GuardSim::build(grid)?;
+    let mut frame = Frame::new(&sim.map.tiles);
+    let mut previous: Option<Position> = None;
+    let frames = sim.map(move |position| {
+        if let Some(Position { row, col, .. }) = previous {
+            frame.cells[[row, col]] = Cell::Visited;
+        }
+        frame.cells[[position.row, position.col]] = Cell::Guard(position.dir);
+        previous = Some(position);
+        frame.clone()
+    });
+    Ok(frames)
+}
+
+// Cells on the loop the guard ends up in once the obstacle is added, walked
+// from where the guard joins the loop with the obstacle laid over the steps
+fn cycle_cells(steps: &Steps, obstacle: &LoopObstacle) -> Vec<(usize, usize)> {
+    let LoopObstacle {
+        row, col, entry, ..
+    } = *obstacle;
+    let overlay = Overlay::new(steps, row, col);
+    let mut cells = Vec::new();
+    let mut position = entry;
+    loop {
+        let next = overlay.get(position).next_position;
+        cells.extend(steps.cells(position, next));
+        position = next;
+        if next == entry {
+            break cells;
+        }
+    }
+}
+
+// One frame per loop-causing obstacle, showing the obstacle and the resulting
+// cycle over the guard's original patrol
+pub fn loop_frames(grid: &Array2<char>) -> anyhow::Result<impl Iterator<Item = Frame>> {
+    let mut sim = GuardSim::build(grid)?;
+    let mut base = Frame::new(&sim.map.tiles);
+    let start = sim.start_position();
+    for Position { row, col, .. } in sim.by_ref() {
+        base.cells[[row, col]] = Cell::Visited;
+    }
+
+    let frames = loop_obstacles(grid).into_iter().map(move |obstacle| {
+        let LoopObstacle { row, col, .. } = obstacle;
+        let mut frame = base.clone();
+        for (r, c) in cycle_cells(&sim.map.steps, &obstacle) {
+            frame.cells[[r, c]] = Cell::Cycle;
+        }
+        frame.cells[[row, col]] = Cell::LoopObstacle;
+        frame.cells[[start.row, start.col]] = Cell::Guard(start.dir);
+        frame
+    });
+    Ok(frames)
+}
+
+// Redraws the terminal with each frame in turn, waiting `delay` between frames
+pub fn play<I, W>(frames: I, delay: Duration, out: &mut W) -> io::Result<()>
+where
+    I: IntoIterator<Item = Frame>,
+    W: Write,
+{
+    for frame in frames {
+        write!(out, "{CLEAR}{}", frame.to_ansi())?;
+        out.flush()?;
+        thread::sleep(delay);
+    }
+    Ok(())
+}
+
+// Writes numbered frame files into `dir`, returning how many were written
+pub fn export<I>(frames: I, dir: &Path, format: FrameFormat) -> io::Result<usize>
+where
+    I: IntoIterator<Item = Frame>,
+{
+    fs::create_dir_all(dir)?;
+    let mut count = 0;
+    for (i, frame) in frames.into_iter().enumerate() {
+        let path = dir.join(format!("frame_{i:05}.{}", format.extension()));
+        match format {
+            FrameFormat::Text => fs::write(path, frame.to_text())?,
+            FrameFormat::Ppm => fs::write(path, frame.to_ppm())?,
+        }
+        count += 1;
+    }
+    Ok(count)
+}